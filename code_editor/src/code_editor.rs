@@ -5,7 +5,6 @@ use {
         selection::Affinity,
         session::{SelectionMode, CodeSession},
         history::{NewGroup},
-        settings::Settings,
         str::StrExt,
         text::Position,
         token::TokenKind,
@@ -816,7 +815,7 @@ impl CodeEditor {
                 was_paste: true,
                 ..
             }) if input.len() > 0 && !self.read_only => {
-                session.paste(input.into());
+                session.paste(input.replace("\r\n", "\n").into());
                 self.redraw(cx);
                 keyboard_moved_cursor = true;
                 actions.push(CodeEditorAction::TextDidChange);
@@ -1147,7 +1146,8 @@ impl CodeEditor {
                                             grapheme,
                                         );
                                         byte_index += grapheme.len();
-                                        column_index += grapheme.column_count();
+                                        column_index += grapheme
+                                            .column_count_at(column_index, line.tab_column_count());
                                     }
                                 }
                             }
@@ -1190,14 +1190,12 @@ impl CodeEditor {
             .layout()
             .block_elements(self.line_start, self.line_end)
         {
-            let Settings {
-                tab_column_count, ..
-            } = **session.settings();
+            let indent_column_count = session.settings().indent_column_count();
             match element {
                 BlockElement::Line { line, .. } => {
                     for row_index in 0..line.row_count() {
                         for column_index in
-                            (0..line.indent_column_count()).step_by(tab_column_count)
+                            (0..line.indent_column_count()).step_by(indent_column_count)
                         {
                             let (x, y) = line.grid_to_normalized_position(row_index, column_index);
                             self.draw_indent_guide.draw_abs(
@@ -1268,219 +1266,16 @@ impl CodeEditor {
             code_editor: self,
             active_selection,
             selections,
+            virtual_space: session.settings().virtual_space,
         }
         .draw_selection_layer(cx, session)
     }
 
     fn pick(&self, session: &CodeSession, position: DVec2) -> ((Position, Affinity), bool) {
         let position = (position - self.viewport_rect.pos) / self.cell_size;
-        
-        if position.y < 0.0 {
-            return (
-                (
-                    Position {
-                        line_index: 0,
-                        byte_index: 0,
-                    },
-                    Affinity::Before,
-                ),
-                false,
-            );
-        }
         let layout = session.layout();
-        if position.y > session.layout().height() {
-            let lines = layout.as_text().as_lines();
-            return (
-                (
-                    Position {
-                        line_index: lines.len() - 1,
-                        byte_index: lines[lines.len() - 1].len(),
-                    },
-                    Affinity::After,
-                ),
-                false,
-            );
-        }
-        let mut line_index = layout.find_first_line_ending_after_y(position.y);
-        let mut origin_y = layout.line(line_index).y();
-        for block in layout.block_elements(line_index, line_index + 1) {
-            match block {
-                BlockElement::Line {
-                    is_inlay: false,
-                    line,
-                } => {
-                    let mut byte_index = 0;
-                    let mut row_index = 0;
-                    let mut column_index = 0;
-                    for element in line.wrapped_elements() {
-                        match element {
-                            WrappedElement::Text {
-                                is_inlay: false,
-                                text,
-                            } => {
-                                for grapheme in text.graphemes() {
-                                    let (start_x, y) =
-                                        line.grid_to_normalized_position(row_index, column_index);
-                                    let start_y = origin_y + y;
-                                    let (end_x, _) = line.grid_to_normalized_position(
-                                        row_index,
-                                        column_index + grapheme.column_count(),
-                                    );
-                                    let end_y = start_y + line.scale();
-                                    if (start_y..=end_y).contains(&position.y) {
-                                        let mid_x = (start_x + end_x) / 2.0;
-                                        if (start_x..=mid_x).contains(&position.x) {
-                                            return (
-                                                (
-                                                    Position {
-                                                        line_index,
-                                                        byte_index,
-                                                    },
-                                                    Affinity::After,
-                                                ),
-                                                false,
-                                            );
-                                        }
-                                        if (mid_x..=end_x).contains(&position.x) {
-                                            return (
-                                                (
-                                                    Position {
-                                                        line_index,
-                                                        byte_index: byte_index + grapheme.len(),
-                                                    },
-                                                    Affinity::Before,
-                                                ),
-                                                false,
-                                            );
-                                        }
-                                    }
-                                    byte_index += grapheme.len();
-                                    column_index += grapheme.column_count();
-                                }
-                            }
-                            WrappedElement::Text {
-                                is_inlay: true,
-                                text,
-                            } => {
-                                let (start_x, y) =
-                                    line.grid_to_normalized_position(row_index, column_index);
-                                let start_y = origin_y + y;
-                                let (end_x, _) = line.grid_to_normalized_position(
-                                    row_index,
-                                    column_index + text.column_count(),
-                                );
-                                let end_y = origin_y + line.scale();
-                                if (start_y..=end_y).contains(&position.y)
-                                    && (start_x..=end_x).contains(&position.x)
-                                {
-                                    return (
-                                        (
-                                            Position {
-                                                line_index,
-                                                byte_index,
-                                            },
-                                            Affinity::Before,
-                                        ),
-                                        false,
-                                    );
-                                }
-                                column_index += text.column_count();
-                            }
-                            WrappedElement::Widget(widget) => {
-                                column_index += widget.column_count;
-                            }
-                            WrappedElement::Wrap => {
-                                let (_, y) =
-                                    line.grid_to_normalized_position(row_index, column_index);
-                                let start_y = origin_y + y;
-                                let end_y = start_y + line.scale();
-                                if (start_y..=end_y).contains(&position.y) {
-                                    return if position.x < 0.0 {
-                                        (
-                                            (
-                                                Position {
-                                                    line_index,
-                                                    byte_index: 0,
-                                                },
-                                                Affinity::Before,
-                                            ),
-                                            true,
-                                        )
-                                    } else {
-                                        (
-                                            (
-                                                Position {
-                                                    line_index,
-                                                    byte_index,
-                                                },
-                                                Affinity::Before,
-                                            ),
-                                            false,
-                                        )
-                                    };
-                                }
-                                column_index = line.wrap_indent_column_count();
-                                row_index += 1;
-                            }
-                        }
-                    }
-                    let (_, y) = line.grid_to_normalized_position(row_index, column_index);
-                    let start_y = origin_y + y;
-                    let end_y = start_y + line.scale();
-                    if (start_y..=end_y).contains(&position.y) {
-                        return if position.x < 0.0 {
-                            (
-                                (
-                                    Position {
-                                        line_index,
-                                        byte_index: 0,
-                                    },
-                                    Affinity::Before,
-                                ),
-                                true,
-                            )
-                        } else {
-                            (
-                                (
-                                    Position {
-                                        line_index,
-                                        byte_index,
-                                    },
-                                    Affinity::Before,
-                                ),
-                                false,
-                            )
-                        };
-                    }
-                    line_index += 1;
-                    origin_y += line.height();
-                }
-                BlockElement::Line {
-                    is_inlay: true,
-                    line,
-                } => {
-                    let start_y = origin_y;
-                    let end_y = start_y + line.height();
-                    if (start_y..=end_y).contains(&position.y) {
-                        return (
-                            (
-                                Position {
-                                    line_index,
-                                    byte_index: 0,
-                                },
-                                Affinity::Before,
-                            ),
-                            false,
-                        );
-                    }
-                    origin_y += line.height();
-                }
-                BlockElement::Widget(widget) => {
-                    origin_y += widget.height;
-                }
-            }
-        }
-        panic!()
+        let is_in_gutter = position.x < 0.0 && (0.0..=layout.height()).contains(&position.y);
+        (layout.hit_test(position.x, position.y), is_in_gutter)
     }
 }
 
@@ -1540,7 +1335,8 @@ impl<'a> DrawDecorationLayer<'a> {
                                         column_index,
                                     );
                                     byte_index += grapheme.len();
-                                    column_index += grapheme.column_count();
+                                    column_index += grapheme
+                                        .column_count_at(column_index, line.tab_column_count());
                                     self.handle_event(
                                         cx,
                                         line_index,
@@ -1688,6 +1484,7 @@ struct DrawSelectionLayer<'a> {
     code_editor: &'a mut CodeEditor,
     active_selection: Option<ActiveSelection>,
     selections: Iter<'a, Selection>,
+    virtual_space: bool,
 }
 
 impl<'a> DrawSelectionLayer<'a> {
@@ -1734,7 +1531,8 @@ impl<'a> DrawSelectionLayer<'a> {
                                         column_index,
                                     );
                                     byte_index += grapheme.len();
-                                    column_index += grapheme.column_count();
+                                    column_index += grapheme
+                                        .column_count_at(column_index, line.tab_column_count());
                                     self.draw_selection_event(
                                         cx,
                                         line_index,
@@ -1839,8 +1637,12 @@ impl<'a> DrawSelectionLayer<'a> {
         {
             let selection = *self.selections.next().unwrap();
             if selection.cursor.position == position && selection.cursor.affinity == affinity {
+                let mut cursor_column_index = column_index;
+                if self.virtual_space && selection.is_empty() {
+                    cursor_column_index += selection.cursor.virtual_column_count(line);
+                }
                 self.draw_cursor_bg(cx, line, origin_y, row_index, column_index);
-                self.draw_cursor(cx, line, origin_y, row_index, column_index);
+                self.draw_cursor(cx, line, origin_y, row_index, cursor_column_index);
             }
             if !selection.is_empty() {
                 let (start_x, _) = line.grid_to_normalized_position(row_index, column_index);
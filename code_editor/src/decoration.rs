@@ -1,5 +1,5 @@
 use {
-    crate::text::{Edit, Length, Position},
+    crate::text::{Change, Edit, Length, Position},
     std::{ops::Deref, slice::Iter},
 };
 
@@ -95,6 +95,19 @@ impl DecorationSet {
     }
 
     pub fn apply_edit(&mut self, edit: &Edit) {
+        if let Change::Delete(start, length) = edit.change {
+            // Decorations that lie entirely inside the deleted range no longer refer to any text,
+            // so drop them instead of collapsing them onto the start of the deletion. Empty
+            // decorations at either end of the deletion mark a position that still exists.
+            let end = start + length;
+            self.decorations.retain(|decoration| {
+                length == Length::zero()
+                    || decoration.start() < start
+                    || decoration.end() > end
+                    || (decoration.is_empty()
+                        && (decoration.start() == start || decoration.start() == end))
+            });
+        }
         for decoration in &mut self.decorations {
             *decoration = decoration.apply_edit(edit);
         }
@@ -0,0 +1,184 @@
+use crate::{
+    char::CharExt,
+    text::Position,
+    token::{Token, TokenKind},
+};
+
+pub(crate) fn find_highlighted_delimiter_pair(
+    lines: &[String],
+    tokens: &[Vec<Token>],
+    position: Position,
+) -> Option<(Position, Position)> {
+    // Cursor is before an opening delimiter
+    match lines[position.line_index][position.byte_index..]
+        .chars()
+        .next()
+    {
+        Some(ch) if ch.is_opening_delimiter() && is_code(tokens, position) => {
+            let opening_delimiter_position = position;
+            if let Some(closing_delimiter_position) = find_closing_delimiter(
+                lines,
+                tokens,
+                Position {
+                    line_index: position.line_index,
+                    byte_index: position.byte_index + ch.len_utf8(),
+                },
+                ch,
+            ) {
+                return Some((opening_delimiter_position, closing_delimiter_position));
+            }
+        }
+        _ => {}
+    }
+    // Cursor is before a closing delimiter
+    match lines[position.line_index][position.byte_index..]
+        .chars()
+        .next()
+    {
+        Some(ch) if ch.is_closing_delimiter() && is_code(tokens, position) => {
+            let closing_delimiter_position = position;
+            if let Some(opening_delimiter_position) =
+                find_opening_delimiter(lines, tokens, position, ch)
+            {
+                return Some((opening_delimiter_position, closing_delimiter_position));
+            }
+        }
+        _ => {}
+    }
+    // Cursor is after a closing delimiter
+    match lines[position.line_index][..position.byte_index]
+        .chars()
+        .next_back()
+    {
+        Some(ch) if ch.is_closing_delimiter() => {
+            let closing_delimiter_position = Position {
+                line_index: position.line_index,
+                byte_index: position.byte_index - ch.len_utf8(),
+            };
+            if is_code(tokens, closing_delimiter_position) {
+                if let Some(opening_delimiter_position) =
+                    find_opening_delimiter(lines, tokens, closing_delimiter_position, ch)
+                {
+                    return Some((opening_delimiter_position, closing_delimiter_position));
+                }
+            }
+        }
+        _ => {}
+    }
+    // Cursor is after an opening delimiter
+    match lines[position.line_index][..position.byte_index]
+        .chars()
+        .next_back()
+    {
+        Some(ch) if ch.is_opening_delimiter() => {
+            let opening_delimiter_position = Position {
+                line_index: position.line_index,
+                byte_index: position.byte_index - ch.len_utf8(),
+            };
+            if is_code(tokens, opening_delimiter_position) {
+                if let Some(closing_delimiter_position) =
+                    find_closing_delimiter(lines, tokens, position, ch)
+                {
+                    return Some((opening_delimiter_position, closing_delimiter_position));
+                }
+            }
+        }
+        _ => {}
+    }
+    None
+}
+
+/// Delimiters inside strings and comments, according to `tokens`, are skipped. Lines without
+/// tokens are treated as code throughout.
+pub(crate) fn find_opening_delimiter(
+    lines: &[String],
+    tokens: &[Vec<Token>],
+    position: Position,
+    closing_delimiter: char,
+) -> Option<Position> {
+    let mut delimiter_stack = vec![closing_delimiter];
+    let mut position = position;
+    loop {
+        for char in lines[position.line_index][..position.byte_index]
+            .chars()
+            .rev()
+        {
+            position.byte_index -= char.len_utf8();
+            if (char.is_opening_delimiter() || char.is_closing_delimiter())
+                && !is_code(tokens, position)
+            {
+                continue;
+            }
+            if char.is_closing_delimiter() {
+                delimiter_stack.push(char);
+            }
+            if char.is_opening_delimiter() {
+                if delimiter_stack.last() != Some(&char.opposite_delimiter().unwrap()) {
+                    return None;
+                }
+                delimiter_stack.pop().unwrap();
+                if delimiter_stack.is_empty() {
+                    return Some(position);
+                }
+            }
+        }
+        if position.line_index == 0 {
+            return None;
+        }
+        position.line_index -= 1;
+        position.byte_index = lines[position.line_index].len();
+    }
+}
+
+fn find_closing_delimiter(
+    lines: &[String],
+    tokens: &[Vec<Token>],
+    position: Position,
+    opening_delimiter: char,
+) -> Option<Position> {
+    let mut delimiter_stack = vec![opening_delimiter];
+    let mut position = position;
+    loop {
+        for char in lines[position.line_index][position.byte_index..].chars() {
+            if (char.is_opening_delimiter() || char.is_closing_delimiter())
+                && !is_code(tokens, position)
+            {
+                position.byte_index += char.len_utf8();
+                continue;
+            }
+            if char.is_opening_delimiter() {
+                delimiter_stack.push(char);
+            }
+            if char.is_closing_delimiter() {
+                if delimiter_stack.last() != Some(&char.opposite_delimiter().unwrap()) {
+                    return None;
+                }
+                delimiter_stack.pop().unwrap();
+                if delimiter_stack.is_empty() {
+                    return Some(position);
+                }
+            }
+            position.byte_index += char.len_utf8();
+        }
+        if position.line_index == lines.len() - 1 {
+            return None;
+        }
+        position.line_index += 1;
+        position.byte_index = 0;
+    }
+}
+
+fn is_code(tokens: &[Vec<Token>], position: Position) -> bool {
+    let tokens = match tokens.get(position.line_index) {
+        Some(tokens) => tokens,
+        None => return true,
+    };
+    let mut byte_index = 0;
+    for token in tokens {
+        byte_index += token.len;
+        if position.byte_index < byte_index {
+            return !matches!(token.kind, TokenKind::Comment | TokenKind::String);
+        }
+    }
+    true
+}
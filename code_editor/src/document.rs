@@ -2,23 +2,27 @@ use {
     crate::{
         char::CharExt,
         decoration::{Decoration, DecorationSet},
-        history::{EditKind, History},
+        delimiter::find_opening_delimiter,
+        history::{EditKind, History, UndoGroupInfo},
         inlays::{BlockInlay, InlineInlay},
         iter::IteratorExt,
-        selection::SelectionSet,
+        selection::{Cursor, Selection, SelectionSet},
         session::SessionId,
         settings::Settings,
         str::StrExt,
-        text::{Change, Drift, Edit, Length, Position, Text},
+        text::{Change, Drift, Edit, Length, LineEnding, Position, Text, TextSnapshot},
         token::{Token, TokenKind},
-        tokenizer::Tokenizer,
+        tokenizer::{DynTokenCache, RustTokenizer, TokenCache, Tokenizer},
     },
     std::{
-        cell::{Ref, RefCell},
+        cell::{Cell, Ref, RefCell},
         cmp::Ordering,
-        collections::HashMap,
+        collections::{BTreeMap, HashMap},
+        fmt,
+        io::{self, BufRead},
         iter,
         ops::Range,
+        mem,
         rc::Rc,
         sync::mpsc::Sender,
     },
@@ -29,6 +33,19 @@ pub struct CodeDocument(Rc<DocumentInner>);
 
 impl CodeDocument {
     pub fn new(text: Text, decorations: DecorationSet) -> Self {
+        Self::with_tokenizer(text, decorations, RustTokenizer::default())
+    }
+
+    /// Like `new`, but highlights the text with `tokenizer` instead of a `RustTokenizer`.
+    pub fn with_tokenizer<T>(mut text: Text, decorations: DecorationSet, tokenizer: T) -> Self
+    where
+        T: Tokenizer + fmt::Debug + 'static,
+        T::State: fmt::Debug,
+    {
+        let line_ending = text.line_ending();
+        if line_ending == LineEnding::CrLf {
+            text.normalize_line_endings();
+        }
         let line_count = text.as_lines().len();
         let tokens: Vec<_> = (0..line_count)
             .map(|line| tokenize(&text.as_lines()[line]).collect::<Vec<_>>())
@@ -41,9 +58,12 @@ impl CodeDocument {
                 inline_inlays: (0..line_count).map(|_| Vec::new()).collect(),
                 block_inlays: Vec::new(),
             }),
-            tokenizer: RefCell::new(Tokenizer::new(line_count)),
+            tokenizer: RefCell::new(Box::new(TokenCache::with_tokenizer(tokenizer, line_count))),
+            provisional_edits: RefCell::new(Vec::new()),
             decorations: RefCell::new(decorations),
-            edit_senders: RefCell::new(HashMap::new()),
+            edit_senders: RefCell::new(BTreeMap::new()),
+            cursor_positions: RefCell::new(HashMap::new()),
+            line_ending: Cell::new(line_ending),
         }));
         inner.update_indent_state();
         inner.0.tokenizer.borrow_mut().update(
@@ -52,39 +72,107 @@ impl CodeDocument {
         );
         inner
     }
+
+    pub fn from_text(text: Text) -> Self {
+        Self::new(text, DecorationSet::new())
+    }
     
-    pub fn replace(&self, new_text: Text) {
+    /// Loads a document without first reading the whole input into memory. Each line is stored
+    /// as its own `String`, alongside its tokens and layout state, so memory use grows with the
+    /// number of lines rather than with the size of a single buffer.
+    pub fn from_reader<R>(reader: R, decorations: DecorationSet) -> io::Result<Self>
+    where
+        R: BufRead,
+    {
+        Ok(Self::new(Text::from_buf_reader(reader)?, decorations))
+    }
+
+    #[deprecated(since = "0.6.0", note = "use `set_text` instead")]
+    pub fn replace(&self, text: Text) {
+        self.set_text(text);
+    }
+
+    /// Replaces the entire text as a single undo group. Rather than updating the document's
+    /// layout line by line, it is rebuilt from scratch.
+    pub fn set_text(&self, mut text: Text) {
+        let line_ending = text.line_ending();
+        if line_ending == LineEnding::CrLf {
+            text.normalize_line_endings();
+        }
+        self.0.line_ending.set(line_ending);
         let mut history = self.0.history.borrow_mut();
-        
-        // Create an edit that deletes the entire existing text.
-        let text_length = history.as_text().length();
-        let delete_edit = Edit {
-            change: Change::Delete(Position::zero(), text_length),
-            drift: Drift::Before,
-        };
-        
-        // Create an edit that inserts the new text at position zero.
-        let insert_edit = Edit {
-            change: Change::Insert(Position::zero(), new_text),
-            drift: Drift::Before,
-        };
-        
-        // Apply the edits to history, starting a new group for undo.
-        history.force_new_group(); // Start a new undo group.
-        history.apply_edit(delete_edit.clone());
-        history.apply_edit(insert_edit.clone());
-        
+        let edits = vec![
+            Edit {
+                change: Change::Delete(Position::zero(), history.as_text().length()),
+                drift: Drift::Before,
+            },
+            Edit {
+                change: Change::Insert(Position::zero(), text),
+                drift: Drift::Before,
+            },
+        ];
+        self.commit_provisional_edits(&mut history);
+        history.push_group(EditKind::Other, &SelectionSet::new());
+        for edit in &edits {
+            history.apply_edit(edit.clone());
+        }
         drop(history);
-        
-        // Now, update the document state after the edits.
-        let edits = vec![delete_edit, insert_edit];
-        self.update_after_edit(None, None, &edits);
+        self.reset_layout();
+        self.send_edits(None, None, &edits);
+    }
+
+    pub fn snapshot(&self) -> TextSnapshot {
+        self.0.history.borrow().snapshot()
     }
 
     pub fn as_text(&self) -> Ref<'_, Text> {
         Ref::map(self.0.history.borrow(), |history| history.as_text())
     }
 
+    pub fn line_ending(&self) -> LineEnding {
+        self.0.line_ending.get()
+    }
+
+    pub fn set_line_ending(&self, line_ending: LineEnding) {
+        self.0.line_ending.set(line_ending);
+    }
+
+    pub fn serialize(&self) -> String {
+        self.as_text().to_string_with_line_ending(self.line_ending())
+    }
+
+    pub fn normalize_line_endings(&self) {
+        let mut history = self.0.history.borrow_mut();
+        let (_, remaining_lines) = history.as_text().as_lines().split_last().unwrap();
+        let edits: Vec<_> = remaining_lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| line.ends_with('\r'))
+            .map(|(line_index, line)| Edit {
+                change: Change::Delete(
+                    Position {
+                        line_index,
+                        byte_index: line.len() - 1,
+                    },
+                    Length {
+                        line_count: 0,
+                        byte_count: 1,
+                    },
+                ),
+                drift: Drift::Before,
+            })
+            .collect();
+        if edits.is_empty() {
+            return;
+        }
+        history.force_new_group();
+        for edit in &edits {
+            history.apply_edit(edit.clone());
+        }
+        drop(history);
+        self.update_after_edit(None, None, &edits);
+    }
+
     pub fn layout(&self) -> Ref<'_, DocumentLayout> {
         self.0.layout.borrow()
     }
@@ -104,6 +192,7 @@ impl CodeDocument {
         mut f: impl FnMut(Editor<'_>, Position, Length),
     ) {
         let mut history = self.0.history.borrow_mut();
+        self.commit_provisional_edits(&mut history);
         history.push_or_extend_group(session_id, kind, selections);
         let mut edits = Vec::new();
         let mut line_ranges = Vec::new();
@@ -162,10 +251,108 @@ impl CodeDocument {
             prev_edit_start = edit_start;
         }
         drop(history);
-        self.autoindent(&line_ranges, settings.tab_column_count, &mut edits);
+        self.autoindent(
+            &line_ranges,
+            settings.indent_column_count(),
+            settings.tab_column_count,
+            &mut edits,
+        );
         self.update_after_edit(Some(session_id), None, &edits);
     }
 
+    pub fn edit(
+        &self,
+        origin_id: SessionId,
+        kind: EditKind,
+        selections: &SelectionSet,
+        f: impl FnOnce(Editor<'_>),
+    ) {
+        let mut history = self.0.history.borrow_mut();
+        self.commit_provisional_edits(&mut history);
+        history.push_or_extend_group(origin_id, kind, selections);
+        let mut edits = Vec::new();
+        f(Editor {
+            history: &mut history,
+            edits: &mut edits,
+        });
+        drop(history);
+        self.update_after_edit(Some(origin_id), None, &edits);
+    }
+
+    /// Applies `edits` without recording them in the history, for text that is only shown while
+    /// it is being composed with an input method.
+    pub fn apply_provisional_edits(&self, origin_id: SessionId, edits: Vec<Edit>) {
+        let mut history = self.0.history.borrow_mut();
+        let mut provisional_edits = self.0.provisional_edits.borrow_mut();
+        for edit in &edits {
+            if provisional_edits
+                .last()
+                .is_some_and(|(session_id, inverted_edit)| {
+                    *session_id == origin_id && inverted_edit.change == edit.change
+                })
+            {
+                provisional_edits.pop();
+            } else {
+                provisional_edits.push((origin_id, edit.clone().invert(history.as_text())));
+            }
+            history.apply_provisional_edit(edit.clone());
+        }
+        drop(provisional_edits);
+        drop(history);
+        self.update_after_edit(Some(origin_id), None, &edits);
+    }
+
+    /// Returns `true` if the session with the given id has provisional edits that have not been
+    /// committed or reverted by a later edit.
+    pub fn has_provisional_edits(&self, session_id: SessionId) -> bool {
+        self.0
+            .provisional_edits
+            .borrow()
+            .iter()
+            .any(|&(id, _)| id == session_id)
+    }
+
+    /// Records the provisional edits of every session as a group of their own, so that the
+    /// recorded edit that follows can be undone without the history getting out of sync. Undoing
+    /// the group puts the cursor where the first provisional edit was made.
+    fn commit_provisional_edits(&self, history: &mut History) {
+        let provisional_edits = mem::take(&mut *self.0.provisional_edits.borrow_mut());
+        let position = match provisional_edits.first() {
+            Some((_, edit)) => match edit.change {
+                Change::Insert(position, _) | Change::Delete(position, _) => position,
+            },
+            None => return,
+        };
+        let mut selections = SelectionSet::new();
+        selections.set_selection(Selection::from(Cursor::from(position)));
+        history.push_group(EditKind::Insert, &selections);
+        history.record_provisional_edits(
+            provisional_edits
+                .into_iter()
+                .map(|(_, inverted_edit)| inverted_edit),
+        );
+    }
+
+    /// Reverts the provisional edits of every session, so that the undo and redo stacks match the
+    /// text again.
+    fn revert_provisional_edits(&self) {
+        let provisional_edits = mem::take(&mut *self.0.provisional_edits.borrow_mut());
+        if provisional_edits.is_empty() {
+            return;
+        }
+        let mut history = self.0.history.borrow_mut();
+        let edits: Vec<_> = provisional_edits
+            .into_iter()
+            .rev()
+            .map(|(_, inverted_edit)| {
+                history.apply_provisional_edit(inverted_edit.clone());
+                inverted_edit
+            })
+            .collect();
+        drop(history);
+        self.update_after_edit(None, None, &edits);
+    }
+
     pub fn edit_linewise(
         &self,
         origin_id: SessionId,
@@ -174,6 +361,7 @@ impl CodeDocument {
         mut f: impl FnMut(Editor, usize),
     ) {
         let mut history = self.0.history.borrow_mut();
+        self.commit_provisional_edits(&mut history);
         history.push_or_extend_group(origin_id, kind, selections);
         let mut edits = Vec::new();
         for line_range in selections
@@ -223,17 +411,42 @@ impl CodeDocument {
 
     pub fn remove_session(&mut self, session_id: SessionId) {
         self.0.edit_senders.borrow_mut().remove(&session_id);
+        self.0.cursor_positions.borrow_mut().remove(&session_id);
+    }
+
+    pub fn session_count(&self) -> usize {
+        self.0.edit_senders.borrow().len()
+    }
+
+    pub fn session_ids(&self) -> Vec<SessionId> {
+        self.0.edit_senders.borrow().keys().copied().collect()
+    }
+
+    pub fn cursor_positions(&self) -> Ref<'_, HashMap<SessionId, Position>> {
+        self.0.cursor_positions.borrow()
+    }
+
+    pub fn set_cursor_position(&self, session_id: SessionId, position: Position) {
+        self.0
+            .cursor_positions
+            .borrow_mut()
+            .insert(session_id, position);
     }
 
     fn autoindent(
         &self,
         line_ranges: &[Range<usize>],
         indent_column_count: usize,
+        tab_column_count: usize,
         edits: &mut Vec<Edit>,
     ) {
-        fn next_line_indent_column_count(line: &str, tab_column_count: usize) -> Option<usize> {
+        fn next_line_indent_column_count(
+            line: &str,
+            indent_column_count: usize,
+            tab_column_count: usize,
+        ) -> Option<usize> {
             if let Some(indent) = line.indent() {
-                let mut indent_column_count = indent.column_count();
+                let mut next_indent_column_count = indent.column_count_at(0, tab_column_count);
                 if line
                     .chars()
                     .rev()
@@ -248,9 +461,9 @@ impl CodeDocument {
                     })
                     .unwrap_or(false)
                 {
-                    indent_column_count += tab_column_count;
+                    next_indent_column_count += indent_column_count;
                 };
-                Some(indent_column_count)
+                Some(next_indent_column_count)
             } else {
                 None
             }
@@ -271,30 +484,48 @@ impl CodeDocument {
                 [..line_range.start]
                 .iter()
                 .rev()
-                .find_map(|line| next_line_indent_column_count(line, indent_column_count))
+                .find_map(|line| {
+                    next_line_indent_column_count(line, indent_column_count, tab_column_count)
+                })
                 .unwrap_or(0);
             for line in line_range {
-                if self.as_text().as_lines()[line]
-                    .chars()
-                    .find_map(|char| {
-                        if char.is_closing_delimiter() {
-                            return Some(true);
-                        }
-                        if !char.is_whitespace() {
-                            return Some(false);
+                let text = self.as_text();
+                let leading_closing_delimiter = text.as_lines()[line]
+                    .char_indices()
+                    .find(|(_, char)| !char.is_whitespace())
+                    .filter(|(_, char)| char.is_closing_delimiter());
+                if let Some((byte_index, char)) = leading_closing_delimiter {
+                    // Line the closing delimiter up with the line of its matching opening
+                    // delimiter, falling back to outdenting by one level if there is none.
+                    // The tokens lag behind the edits made so far, so they are not used here.
+                    desired_indentation_column_count = match find_opening_delimiter(
+                        text.as_lines(),
+                        &[],
+                        Position {
+                            line_index: line,
+                            byte_index,
+                        },
+                        char,
+                    ) {
+                        Some(position) => text.as_lines()[position.line_index]
+                            .indent()
+                            .unwrap_or("")
+                            .column_count_at(0, tab_column_count),
+                        None => {
+                            desired_indentation_column_count.saturating_sub(indent_column_count)
                         }
-                        None
-                    })
-                    .unwrap_or(false)
-                {
-                    desired_indentation_column_count -= 4;
+                    };
                 }
+                drop(text);
                 self.edit_lines_internal(line, edits, |line| {
-                    crate::session::reindent(line, |_| desired_indentation_column_count)
+                    crate::session::reindent(line, tab_column_count, |_| {
+                        desired_indentation_column_count
+                    })
                 });
                 if let Some(next_line_indentation_column_count) = next_line_indent_column_count(
                     &self.as_text().as_lines()[line],
                     indent_column_count,
+                    tab_column_count,
                 ) {
                     desired_indentation_column_count = next_line_indentation_column_count;
                 }
@@ -348,6 +579,7 @@ impl CodeDocument {
     }
 
     pub fn undo(&self, origin_id: SessionId, selections: &SelectionSet) -> bool {
+        self.revert_provisional_edits();
         let mut changes = Vec::new();
         let selections = self.0.history.borrow_mut().undo(selections, &mut changes);
         if let Some(selections) = selections {
@@ -359,6 +591,7 @@ impl CodeDocument {
     }
 
     pub fn redo(&self, origin_id: SessionId, selections: &SelectionSet) -> bool {
+        self.revert_provisional_edits();
         let mut changes = Vec::new();
         let selections = self.0.history.borrow_mut().redo(selections, &mut changes);
         if let Some(selections) = selections {
@@ -369,6 +602,39 @@ impl CodeDocument {
         }
     }
 
+    pub fn is_modified(&self) -> bool {
+        self.0.history.borrow().is_modified()
+    }
+
+    pub fn mark_saved(&self) {
+        self.0.history.borrow_mut().mark_saved()
+    }
+
+    pub fn undo_groups(&self) -> Vec<UndoGroupInfo> {
+        self.0.history.borrow().undo_groups()
+    }
+
+    pub fn jump_to_undo_group(
+        &self,
+        origin_id: SessionId,
+        selections: &SelectionSet,
+        group_count: usize,
+    ) -> bool {
+        self.revert_provisional_edits();
+        let mut changes = Vec::new();
+        let selections = self
+            .0
+            .history
+            .borrow_mut()
+            .jump_to_group(selections, group_count, &mut changes);
+        if let Some(selections) = selections {
+            self.update_after_edit(Some(origin_id), Some(selections), &changes);
+            true
+        } else {
+            false
+        }
+    }
+
     fn update_after_edit(
         &self,
         origin_id: Option<SessionId>,
@@ -409,11 +675,45 @@ impl CodeDocument {
             self.0.history.borrow().as_text(),
             &mut self.0.layout.borrow_mut().tokens,
         );
+        self.send_edits(origin_id, selections, edits);
+    }
+
+    fn reset_layout(&self) {
+        let history = self.0.history.borrow();
+        let lines = history.as_text().as_lines();
+        let mut layout = self.0.layout.borrow_mut();
+        layout.indent_state = lines.iter().map(|_| None).collect();
+        layout.tokens = lines
+            .iter()
+            .map(|line| tokenize(line).collect::<Vec<_>>())
+            .collect();
+        layout.inline_inlays = lines.iter().map(|_| Vec::new()).collect();
+        self.0.tokenizer.borrow_mut().reset(lines.len());
+        drop(layout);
+        drop(history);
+        self.update_indent_state();
+        self.0.tokenizer.borrow_mut().update(
+            self.0.history.borrow().as_text(),
+            &mut self.0.layout.borrow_mut().tokens,
+        );
+    }
+
+    fn send_edits(
+        &self,
+        origin_id: Option<SessionId>,
+        selections: Option<SelectionSet>,
+        edits: &[Edit],
+    ) {
         let mut decorations = self.0.decorations.borrow_mut();
         for edit in edits {
             decorations.apply_edit(edit);
         }
         drop(decorations);
+        for position in self.0.cursor_positions.borrow_mut().values_mut() {
+            for edit in edits {
+                *position = position.apply_edit(edit);
+            }
+        }
         for (&session_id, edit_sender) in &*self.0.edit_senders.borrow() {
             if Some(session_id) == origin_id {
                 edit_sender
@@ -710,9 +1010,15 @@ impl<'a> Editor<'a> {
 struct DocumentInner {
     history: RefCell<History>,
     layout: RefCell<DocumentLayout>,
-    tokenizer: RefCell<Tokenizer>,
+    tokenizer: RefCell<Box<dyn DynTokenCache>>,
+    // The inverses of the provisional edits that have not been recorded yet, with the sessions
+    // that made them.
+    provisional_edits: RefCell<Vec<(SessionId, Edit)>>,
     decorations: RefCell<DecorationSet>,
-    edit_senders: RefCell<HashMap<SessionId, Sender<(Option<SelectionSet>, Vec<Edit>)>>>,
+    // Ordered by session id, so that every edit reaches the sessions in the same order.
+    edit_senders: RefCell<BTreeMap<SessionId, Sender<(Option<SelectionSet>, Vec<Edit>)>>>,
+    cursor_positions: RefCell<HashMap<SessionId, Position>>,
+    line_ending: Cell<LineEnding>,
 }
 
 fn tokenize(text: &str) -> impl Iterator<Item = Token> + '_ {
@@ -1,15 +1,21 @@
-use crate::{
-    selection::SelectionSet,
-    session::SessionId,
-    text::{Edit, Text},
+use {
+    crate::{
+        selection::SelectionSet,
+        session::SessionId,
+        text::{Edit, Text, TextSnapshot},
+    },
+    std::sync::Arc,
 };
 
 #[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
 pub struct History {
-    text: Text,
+    // Shared with any snapshots that are still alive, and copied on the first edit after that.
+    text: Arc<Text>,
     current_desc: Option<GroupDesc>,
     undo_stack: Stack,
     redo_stack: Stack,
+    next_group_id: usize,
+    saved_version: Version,
 }
 
 #[derive(Clone,Copy)]
@@ -27,6 +33,10 @@ impl History {
         &self.text
     }
 
+    pub fn snapshot(&self) -> TextSnapshot {
+        TextSnapshot::new(self.text.clone())
+    }
+
     pub fn force_new_group(&mut self) {
         self.current_desc = None;
     }
@@ -45,28 +55,54 @@ impl History {
             .current_desc
             .map_or(false, |current_desc| current_desc.can_merge_with(desc))
         {
-            self.undo_stack.push_group(selections.clone());
+            self.push_group(edit_kind, selections);
             self.current_desc = Some(desc);
         }
     }
 
+    /// Starts a new group that no later edit will be merged into, for edits that do not belong
+    /// to any session.
+    pub fn push_group(&mut self, edit_kind: EditKind, selections: &SelectionSet) {
+        self.undo_stack
+            .push_group(selections.clone(), edit_kind, self.next_group_id);
+        self.next_group_id += 1;
+        self.current_desc = None;
+    }
+
     pub fn apply_edit(&mut self, edit: Edit) {
         let inverted_edit = edit.clone().invert(&self.text);
-        self.text.apply_change(edit.change);
+        Arc::make_mut(&mut self.text).apply_change(edit.change);
         self.undo_stack.push_edit(inverted_edit);
         self.redo_stack.clear();
     }
 
+    /// Applies `edit` without recording it, so it can be neither undone nor redone. The caller
+    /// must revert or record it before the next recorded edit, or the undo stack no longer matches
+    /// the text.
+    pub fn apply_provisional_edit(&mut self, edit: Edit) {
+        Arc::make_mut(&mut self.text).apply_change(edit.change);
+    }
+
+    /// Records edits that were applied with `apply_provisional_edit` as part of the current group,
+    /// given their inverses in the order in which the edits were applied.
+    pub fn record_provisional_edits(&mut self, inverted_edits: impl IntoIterator<Item = Edit>) {
+        for inverted_edit in inverted_edits {
+            self.undo_stack.push_edit(inverted_edit);
+            self.redo_stack.clear();
+        }
+    }
+
     pub fn undo(
         &mut self,
         selections: &SelectionSet,
         edits: &mut Vec<Edit>,
     ) -> Option<SelectionSet> {
-        if let Some(new_selections) = self.undo_stack.pop_group(edits) {
-            self.redo_stack.push_group(selections.clone());
+        if let Some((new_selections, edit_kind, id)) = self.undo_stack.pop_group(edits) {
+            self.redo_stack
+                .push_group(selections.clone(), edit_kind, id);
             for edit in edits {
                 let inverted_edit = edit.clone().invert(&self.text);
-                self.text.apply_change(edit.change.clone());
+                Arc::make_mut(&mut self.text).apply_change(edit.change.clone());
                 self.redo_stack.push_edit(inverted_edit);
             }
             self.current_desc = None;
@@ -81,11 +117,12 @@ impl History {
         selections: &SelectionSet,
         edits: &mut Vec<Edit>,
     ) -> Option<SelectionSet> {
-        if let Some(new_selections) = self.redo_stack.pop_group(edits) {
-            self.undo_stack.push_group(selections.clone());
+        if let Some((new_selections, edit_kind, id)) = self.redo_stack.pop_group(edits) {
+            self.undo_stack
+                .push_group(selections.clone(), edit_kind, id);
             for edit in edits {
                 let inverted_edit = edit.clone().invert(&self.text);
-                self.text.apply_change(edit.change.clone());
+                Arc::make_mut(&mut self.text).apply_change(edit.change.clone());
                 self.undo_stack.push_edit(inverted_edit);
             }
             self.current_desc = None;
@@ -95,15 +132,73 @@ impl History {
         }
     }
 
+    /// Returns every group in the history, oldest first. The groups that have been undone, and
+    /// can be redone, come last.
+    pub fn undo_groups(&self) -> Vec<UndoGroupInfo> {
+        let mut groups: Vec<_> = self.undo_stack.group_infos(false).collect();
+        groups.extend(self.redo_stack.group_infos(true).rev());
+        groups
+    }
+
+    pub fn undo_group_count(&self) -> usize {
+        self.undo_stack.groups.len()
+    }
+
+    /// Undoes or redoes groups until exactly the first `group_count` groups are applied, and
+    /// returns the selections from before the last group that was undone or redone.
+    pub fn jump_to_group(
+        &mut self,
+        selections: &SelectionSet,
+        group_count: usize,
+        edits: &mut Vec<Edit>,
+    ) -> Option<SelectionSet> {
+        let mut new_selections = None;
+        while self.undo_group_count() > group_count {
+            let mut group_edits = Vec::new();
+            new_selections = self.undo(
+                new_selections.as_ref().unwrap_or(selections),
+                &mut group_edits,
+            );
+            edits.extend(group_edits);
+        }
+        while self.undo_group_count() < group_count {
+            let mut group_edits = Vec::new();
+            match self.redo(
+                new_selections.as_ref().unwrap_or(selections),
+                &mut group_edits,
+            ) {
+                Some(selections) => new_selections = Some(selections),
+                None => break,
+            }
+            edits.extend(group_edits);
+        }
+        new_selections
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.version() != self.saved_version
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.saved_version = self.version();
+    }
+
     pub fn into_text(self) -> Text {
-        self.text
+        Arc::try_unwrap(self.text).unwrap_or_else(|text| (*text).clone())
+    }
+
+    fn version(&self) -> Version {
+        Version {
+            group_id: self.undo_stack.groups.last().map(|group| group.id),
+            edit_count: self.undo_stack.edits.len(),
+        }
     }
 }
 
 impl From<Text> for History {
     fn from(text: Text) -> Self {
         Self {
-            text,
+            text: Arc::new(text),
             ..Self::default()
         }
     }
@@ -121,6 +216,17 @@ pub enum EditKind {
 }
 
 impl EditKind {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::Insert | Self::InsertSpace => "Typing",
+            Self::Delete => "Delete",
+            Self::Indent => "Indent",
+            Self::Group(_) => "Replace",
+            Self::Outdent => "Outdent",
+            Self::Other => "Edit",
+        }
+    }
+
     fn can_merge_with(self, other: Self) -> bool {
         if self == Self::Other {
             return false;
@@ -129,6 +235,22 @@ impl EditKind {
     }
 }
 
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct UndoGroupInfo {
+    pub label: &'static str,
+    pub edit_count: usize,
+    pub is_undone: bool,
+}
+
+// Identifies a state of the text by the group on top of the undo stack, which is enough to tell
+// states apart across undo and redo, and the number of edits, which changes when that group is
+// extended.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+struct Version {
+    group_id: Option<usize>,
+    edit_count: usize,
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 struct GroupDesc {
     session_id: SessionId,
@@ -148,9 +270,11 @@ struct Stack {
 }
 
 impl Stack {
-    fn push_group(&mut self, selections: SelectionSet) {
+    fn push_group(&mut self, selections: SelectionSet, edit_kind: EditKind, id: usize) {
         self.groups.push(Group {
             selections,
+            edit_kind,
+            id,
             edit_start: self.edits.len(),
         });
     }
@@ -159,16 +283,30 @@ impl Stack {
         self.edits.push(edit);
     }
 
-    fn pop_group(&mut self, edits: &mut Vec<Edit>) -> Option<SelectionSet> {
+    fn pop_group(&mut self, edits: &mut Vec<Edit>) -> Option<(SelectionSet, EditKind, usize)> {
         match self.groups.pop() {
             Some(group) => {
                 edits.extend(self.edits.drain(group.edit_start..).rev());
-                Some(group.selections)
+                Some((group.selections, group.edit_kind, group.id))
             }
             None => None,
         }
     }
 
+    fn group_infos(&self, is_undone: bool) -> impl DoubleEndedIterator<Item = UndoGroupInfo> + '_ {
+        self.groups.iter().enumerate().map(move |(index, group)| {
+            let edit_end = self
+                .groups
+                .get(index + 1)
+                .map_or(self.edits.len(), |next_group| next_group.edit_start);
+            UndoGroupInfo {
+                label: group.edit_kind.label(),
+                edit_count: edit_end - group.edit_start,
+                is_undone,
+            }
+        })
+    }
+
     fn clear(&mut self) {
         self.groups.clear();
         self.edits.clear();
@@ -178,5 +316,7 @@ impl Stack {
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 struct Group {
     selections: SelectionSet,
+    edit_kind: EditKind,
+    id: usize,
     edit_start: usize,
 }
@@ -67,6 +67,204 @@ impl<'a> Layout<'a> {
         (x, line.y() + y)
     }
 
+    /// Returns the position and affinity under the point (`x`, `y`), given in cells. A point in
+    /// the indent of a wrapped row resolves to the start of that row rather than the end of the
+    /// row above it.
+    pub fn hit_test(&self, x: f64, y: f64) -> (Position, Affinity) {
+        if y < 0.0 {
+            return (
+                Position {
+                    line_index: 0,
+                    byte_index: 0,
+                },
+                Affinity::Before,
+            );
+        }
+        if y > self.height() {
+            let lines = self.as_text().as_lines();
+            return (
+                Position {
+                    line_index: lines.len() - 1,
+                    byte_index: lines[lines.len() - 1].len(),
+                },
+                Affinity::After,
+            );
+        }
+        let mut line_index = self.find_first_line_ending_after_y(y);
+        let mut origin_y = self.line(line_index).y();
+        for block in self.block_elements(line_index, line_index + 1) {
+            match block {
+                BlockElement::Line {
+                    is_inlay: false,
+                    line,
+                } => {
+                    let mut byte_index = 0;
+                    let mut row_index = 0;
+                    let mut column_index = 0;
+                    for element in line.wrapped_elements() {
+                        match element {
+                            WrappedElement::Text {
+                                is_inlay: false,
+                                text,
+                            } => {
+                                for grapheme in text.graphemes() {
+                                    let (start_x, row_y) =
+                                        line.grid_to_normalized_position(row_index, column_index);
+                                    let start_y = origin_y + row_y;
+                                    let (end_x, _) = line.grid_to_normalized_position(
+                                        row_index,
+                                        column_index
+                                            + grapheme.column_count_at(
+                                                column_index,
+                                                line.tab_column_count(),
+                                            ),
+                                    );
+                                    let end_y = start_y + line.scale();
+                                    if (start_y..=end_y).contains(&y) {
+                                        let mid_x = (start_x + end_x) / 2.0;
+                                        if (start_x..=mid_x).contains(&x) {
+                                            return (
+                                                Position {
+                                                    line_index,
+                                                    byte_index,
+                                                },
+                                                Affinity::After,
+                                            );
+                                        }
+                                        if (mid_x..=end_x).contains(&x) {
+                                            return (
+                                                Position {
+                                                    line_index,
+                                                    byte_index: byte_index + grapheme.len(),
+                                                },
+                                                Affinity::Before,
+                                            );
+                                        }
+                                    }
+                                    byte_index += grapheme.len();
+                                    column_index += grapheme
+                                        .column_count_at(column_index, line.tab_column_count());
+                                }
+                            }
+                            WrappedElement::Text {
+                                is_inlay: true,
+                                text,
+                            } => {
+                                let (start_x, row_y) =
+                                    line.grid_to_normalized_position(row_index, column_index);
+                                let start_y = origin_y + row_y;
+                                let (end_x, _) = line.grid_to_normalized_position(
+                                    row_index,
+                                    column_index + text.column_count(),
+                                );
+                                let end_y = origin_y + line.scale();
+                                if (start_y..=end_y).contains(&y) && (start_x..=end_x).contains(&x)
+                                {
+                                    return (
+                                        Position {
+                                            line_index,
+                                            byte_index,
+                                        },
+                                        Affinity::Before,
+                                    );
+                                }
+                                column_index += text.column_count();
+                            }
+                            WrappedElement::Widget(widget) => {
+                                column_index += widget.column_count;
+                            }
+                            WrappedElement::Wrap => {
+                                let (_, row_y) =
+                                    line.grid_to_normalized_position(row_index, column_index);
+                                let start_y = origin_y + row_y;
+                                let end_y = start_y + line.scale();
+                                if (start_y..=end_y).contains(&y) {
+                                    return if x < 0.0 {
+                                        (
+                                            Position {
+                                                line_index,
+                                                byte_index: 0,
+                                            },
+                                            Affinity::Before,
+                                        )
+                                    } else {
+                                        (
+                                            Position {
+                                                line_index,
+                                                byte_index,
+                                            },
+                                            Affinity::Before,
+                                        )
+                                    };
+                                }
+                                column_index = line.wrap_indent_column_count();
+                                row_index += 1;
+                                let (start_x, row_y) =
+                                    line.grid_to_normalized_position(row_index, column_index);
+                                let start_y = origin_y + row_y;
+                                let end_y = start_y + line.scale();
+                                if (start_y..=end_y).contains(&y) && (0.0..start_x).contains(&x) {
+                                    return (
+                                        Position {
+                                            line_index,
+                                            byte_index,
+                                        },
+                                        Affinity::After,
+                                    );
+                                }
+                            }
+                        }
+                    }
+                    let (_, row_y) = line.grid_to_normalized_position(row_index, column_index);
+                    let start_y = origin_y + row_y;
+                    let end_y = start_y + line.scale();
+                    if (start_y..=end_y).contains(&y) {
+                        return if x < 0.0 {
+                            (
+                                Position {
+                                    line_index,
+                                    byte_index: 0,
+                                },
+                                Affinity::Before,
+                            )
+                        } else {
+                            (
+                                Position {
+                                    line_index,
+                                    byte_index,
+                                },
+                                Affinity::Before,
+                            )
+                        };
+                    }
+                    line_index += 1;
+                    origin_y += line.height();
+                }
+                BlockElement::Line {
+                    is_inlay: true,
+                    line,
+                } => {
+                    let start_y = origin_y;
+                    let end_y = start_y + line.height();
+                    if (start_y..=end_y).contains(&y) {
+                        return (
+                            Position {
+                                line_index,
+                                byte_index: 0,
+                            },
+                            Affinity::Before,
+                        );
+                    }
+                    origin_y += line.height();
+                }
+                BlockElement::Widget(widget) => {
+                    origin_y += widget.height;
+                }
+            }
+        }
+        panic!()
+    }
+
     pub fn line(&self, index: usize) -> Line<'_> {
         Line {
             y: self.session_layout.y.get(index).copied(),
@@ -78,6 +276,7 @@ impl<'a> Layout<'a> {
             tokens: &self.document_layout.tokens[index],
             inlays: &self.document_layout.inline_inlays[index],
             wrap_data: self.session_layout.wrap_data[index].as_ref(),
+            tab_column_count: self.session_layout.tab_column_count,
         }
     }
 
@@ -94,6 +293,7 @@ impl<'a> Layout<'a> {
             tokens: self.document_layout.tokens[start..end].iter(),
             inline_inlays: self.document_layout.inline_inlays[start..end].iter(),
             wrap_data: self.session_layout.wrap_data[start..end].iter(),
+            tab_column_count: self.session_layout.tab_column_count,
         }
     }
 
@@ -125,6 +325,7 @@ pub struct Lines<'a> {
     tokens: Iter<'a, Vec<Token>>,
     inline_inlays: Iter<'a, Vec<(usize, InlineInlay)>>,
     wrap_data: Iter<'a, Option<WrapData>>,
+    tab_column_count: usize,
 }
 
 impl<'a> Iterator for Lines<'a> {
@@ -142,6 +343,7 @@ impl<'a> Iterator for Lines<'a> {
             tokens: self.tokens.next().unwrap(),
             inlays: self.inline_inlays.next().unwrap(),
             wrap_data: self.wrap_data.next().unwrap().as_ref(),
+            tab_column_count: self.tab_column_count,
         })
     }
 }
@@ -157,6 +359,7 @@ pub struct Line<'a> {
     pub tokens: &'a [Token],
     pub inlays: &'a [(usize, InlineInlay)],
     pub wrap_data: Option<&'a WrapData>,
+    pub tab_column_count: usize,
 }
 
 impl<'a> Line<'a> {
@@ -207,7 +410,8 @@ impl<'a> Line<'a> {
                             return (current_row_index, current_column_index);
                         }
                         current_byte_index += grapheme.len();
-                        current_column_index += grapheme.column_count();
+                        current_column_index +=
+                            grapheme.column_count_at(current_column_index, self.tab_column_count);
                         if current_byte_index == byte_index && affinity == Affinity::Before {
                             return (current_row_index, current_column_index);
                         }
@@ -249,7 +453,8 @@ impl<'a> Line<'a> {
                     text,
                 } => {
                     for grapheme in text.graphemes() {
-                        let next_column = current_column_index + grapheme.column_count();
+                        let next_column = current_column_index
+                            + grapheme.column_count_at(current_column_index, self.tab_column_count);
                         if current_row_index == row_index
                             && (current_column_index..next_column).contains(&column_index)
                         {
@@ -310,6 +515,10 @@ impl<'a> Line<'a> {
         self.wrap_data.unwrap().indent_column_count
     }
 
+    pub fn tab_column_count(&self) -> usize {
+        self.tab_column_count
+    }
+
     pub fn text(&self) -> &str {
         self.text
     }
@@ -4,6 +4,7 @@ use makepad_widgets::*;
 pub mod char;
 pub mod code_editor;
 pub mod decoration;
+mod delimiter;
 pub mod document;
 pub mod history;
 pub mod inlays;
@@ -21,8 +22,15 @@ pub mod wrap;
 pub mod code_view;
 
 pub use self::{
-    code_editor::CodeEditor, document::CodeDocument, history::History, layout::Line,
-    selection::Selection, session::CodeSession, settings::Settings, token::Token, tokenizer::Tokenizer,
+    code_editor::CodeEditor,
+    document::CodeDocument,
+    history::History,
+    layout::Line,
+    selection::Selection,
+    session::CodeSession,
+    settings::Settings,
+    token::Token,
+    tokenizer::{RustTokenizer, TokenCache, Tokenizer},
 };
 
 pub fn live_design(cx: &mut Cx) {
@@ -1,8 +1,10 @@
 use {
     crate::{
-        layout::Layout,
+        delimiter::find_highlighted_delimiter_pair,
+        layout::{Layout, Line},
         str::StrExt,
         text::{Edit, Length, Position},
+        token::Token,
     },
     std::{ops, ops::Deref, slice::Iter},
 };
@@ -18,6 +20,25 @@ impl Selection {
         self.anchor == self.cursor.position
     }
 
+    /// Returns `true` if `position` lies between the start and end of the selection, both
+    /// inclusive. An empty selection only contains its cursor position.
+    pub fn contains(self, position: Position) -> bool {
+        self.start() <= position && position <= self.end()
+    }
+
+    /// Returns `true` if the two selections share any text, or if one of them is empty and lies
+    /// within the other. Unlike `overlaps_with`, selections that merely touch do not overlap, and
+    /// the order of the selections does not matter.
+    pub fn overlaps(self, other: Self) -> bool {
+        if self.is_empty() {
+            other.contains(self.cursor.position)
+        } else if other.is_empty() {
+            self.contains(other.cursor.position)
+        } else {
+            self.start() < other.end() && other.start() < self.end()
+        }
+    }
+
     pub fn overlaps_with(self, other: Self) -> bool {
         if self.is_empty() || other.is_empty() {
             self.end() >= other.start()
@@ -282,7 +303,20 @@ impl Cursor {
         row == line.row_count() - 1
     }
 
-    pub fn move_left(self, lines: &[String]) -> Self {
+    pub fn is_at_wrap(self, layout: &Layout<'_>) -> bool {
+        let line = layout.line(self.position.line_index);
+        let (row_before, _) =
+            line.logical_to_grid_position(self.position.byte_index, Affinity::Before);
+        let (row_after, _) =
+            line.logical_to_grid_position(self.position.byte_index, Affinity::After);
+        row_before != row_after
+    }
+
+    pub fn move_left(self, layout: &Layout<'_>) -> Self {
+        let lines = layout.as_text().as_lines();
+        if self.affinity == Affinity::After && self.is_at_wrap(layout) {
+            return self.move_to_end_of_prev_row();
+        }
         if !self.is_at_start_of_line() {
             return self.move_to_prev_grapheme(lines);
         }
@@ -292,7 +326,11 @@ impl Cursor {
         self
     }
 
-    pub fn move_right(self, lines: &[String]) -> Self {
+    pub fn move_right(self, layout: &Layout<'_>) -> Self {
+        let lines = layout.as_text().as_lines();
+        if self.affinity == Affinity::Before && self.is_at_wrap(layout) {
+            return self.move_to_start_of_next_row();
+        }
         if !self.is_at_end_of_line(lines) {
             return self.move_to_next_grapheme(lines);
         }
@@ -401,6 +439,85 @@ impl Cursor {
         }
     }
 
+    /// Moves to the start of the next blank line that follows a non-blank one, where a blank line
+    /// is empty or only contains whitespace, or to the end of the last line if there is none.
+    pub fn move_to_next_paragraph(self, lines: &[String]) -> Self {
+        let is_blank = |line_index: usize| lines[line_index].trim().is_empty();
+        let mut line_index = self.position.line_index;
+        while line_index < lines.len() && is_blank(line_index) {
+            line_index += 1;
+        }
+        while line_index < lines.len() && !is_blank(line_index) {
+            line_index += 1;
+        }
+        if line_index == lines.len() {
+            return self.move_to_file_end(lines);
+        }
+        Self {
+            position: Position {
+                line_index,
+                byte_index: 0,
+            },
+            affinity: Affinity::Before,
+            preferred_column_index: None,
+        }
+    }
+
+    /// Moves to the start of the previous blank line that precedes a non-blank one, or to the
+    /// start of the first line if there is none.
+    pub fn move_to_prev_paragraph(self, lines: &[String]) -> Self {
+        let is_blank = |line_index: usize| lines[line_index].trim().is_empty();
+        let mut line_index = self.position.line_index;
+        while line_index > 0 && is_blank(line_index) {
+            line_index -= 1;
+        }
+        while line_index > 0 && !is_blank(line_index) {
+            line_index -= 1;
+        }
+        Self {
+            position: Position {
+                line_index,
+                byte_index: 0,
+            },
+            affinity: Affinity::Before,
+            preferred_column_index: None,
+        }
+    }
+
+    /// Jumps to the other side of the delimiter pair next to the cursor: from just before the
+    /// opening delimiter to just after the closing one, and from just after the opening delimiter
+    /// to just before the closing one, and back. Delimiters inside strings and comments are
+    /// skipped. Inside an empty pair, the cursor jumps to just before the opening delimiter.
+    /// Without a matched delimiter next to the cursor, the cursor stays where it is.
+    pub fn move_to_matching_bracket(self, lines: &[String], tokens: &[Vec<Token>]) -> Self {
+        let (opening, closing) =
+            match find_highlighted_delimiter_pair(lines, tokens, self.position) {
+                Some(pair) => pair,
+                None => return self,
+            };
+        // All delimiters are a single byte long.
+        let after = |position: Position| Position {
+            line_index: position.line_index,
+            byte_index: position.byte_index + 1,
+        };
+        let position = if self.position == opening {
+            after(closing)
+        } else if after(opening) == closing {
+            opening
+        } else if self.position == after(opening) {
+            closing
+        } else if self.position == closing {
+            after(opening)
+        } else {
+            opening
+        };
+        Self {
+            position,
+            affinity: Affinity::Before,
+            preferred_column_index: None,
+        }
+    }
+
     pub fn move_to_prev_grapheme(self, lines: &[String]) -> Self {
         Self {
             position: Position {
@@ -455,6 +572,22 @@ impl Cursor {
         }
     }
 
+    pub fn move_to_end_of_prev_row(self) -> Self {
+        Self {
+            affinity: Affinity::Before,
+            preferred_column_index: None,
+            ..self
+        }
+    }
+
+    pub fn move_to_start_of_next_row(self) -> Self {
+        Self {
+            affinity: Affinity::After,
+            preferred_column_index: None,
+            ..self
+        }
+    }
+
     pub fn move_to_prev_row_of_line(self, layout: &Layout<'_>) -> Self {
         let line = layout.line(self.position.line_index);
         let (row_index, mut column_index) =
@@ -531,6 +664,19 @@ impl Cursor {
     }
 
 
+    /// Returns how many columns past the end of the given line the cursor's preferred column
+    /// lies, which is where the cursor is drawn when virtual space is enabled.
+    pub fn virtual_column_count(self, line: Line<'_>) -> usize {
+        match self.preferred_column_index {
+            Some(preferred_column_index) if self.position.byte_index == line.text().len() => {
+                let (_, column_index) =
+                    line.logical_to_grid_position(self.position.byte_index, self.affinity);
+                preferred_column_index.saturating_sub(column_index)
+            }
+            _ => 0,
+        }
+    }
+
     pub fn apply_edit(self, edit: &Edit) -> Self {
         Self {
             position: self.position.apply_edit(edit),
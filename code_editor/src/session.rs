@@ -1,8 +1,10 @@
 use {
     crate::{
         char::CharExt,
+        delimiter::find_highlighted_delimiter_pair,
         document::CodeDocument,
         history::{EditKind,NewGroup},
+        iter::IteratorExt,
         layout::{BlockElement, Layout, WrappedElement},
         selection::{Affinity, Cursor, SelectionSet},
         str::StrExt,
@@ -11,11 +13,12 @@ use {
         wrap::WrapData,
         Selection, Settings,
     },
+    makepad_widgets::{dvec2, DVec2},
     std::{
         cell::{Cell, Ref, RefCell},
         collections::HashSet,
-        fmt::Write,
         iter, mem,
+        ops::Range,
         rc::Rc,
         sync::{atomic, atomic::AtomicUsize, mpsc, mpsc::Receiver},
     },
@@ -29,6 +32,9 @@ pub struct CodeSession {
     layout: RefCell<SessionLayout>,
     selection_state: RefCell<SelectionState>,
     wrap_column: Cell<Option<usize>>,
+    last_wrap_column: Cell<Option<usize>>,
+    overtype: Cell<bool>,
+    scroll_offset: Cell<DVec2>,
     fold_state: RefCell<FoldState>,
     edit_receiver: Receiver<(Option<SelectionSet>, Vec<Edit>)>,
 }
@@ -39,9 +45,9 @@ impl CodeSession {
 
         let (edit_sender, edit_receiver) = mpsc::channel();
         let line_count = document.as_text().as_lines().len();
+        let settings = Rc::new(Settings::default());
         let mut session = Self {
             id: SessionId(ID.fetch_add(1, atomic::Ordering::AcqRel)),
-            settings: Rc::new(Settings::default()),
             document,
             layout: RefCell::new(SessionLayout {
                 y: Vec::new(),
@@ -49,6 +55,7 @@ impl CodeSession {
                 fold_column: (0..line_count).map(|_| 0).collect(),
                 scale: (0..line_count).map(|_| 1.0).collect(),
                 wrap_data: (0..line_count).map(|_| None).collect(),
+                tab_column_count: settings.tab_column_count,
             }),
             selection_state: RefCell::new(SelectionState {
                 mode: SelectionMode::Simple,
@@ -56,13 +63,21 @@ impl CodeSession {
                 last_added_selection_index: Some(0),
                 injected_char_stack: Vec::new(),
                 highlighted_delimiter_positions: HashSet::new(),
+                snippet_stops: Vec::new(),
+                snippet_stop_index: 0,
+                composition_range: None,
             }),
             wrap_column: Cell::new(None),
+            last_wrap_column: Cell::new(None),
+            overtype: Cell::new(false),
+            scroll_offset: Cell::new(DVec2::default()),
             fold_state: RefCell::new(FoldState {
                 folding_lines: HashSet::new(),
                 folded_lines: HashSet::new(),
                 unfolding_lines: HashSet::new(),
+                animation_finished: false,
             }),
+            settings,
             edit_receiver,
         };
         for line in 0..line_count {
@@ -70,6 +85,7 @@ impl CodeSession {
         }
         session.update_y();
         session.document.add_session(session.id, edit_sender);
+        session.update_cursor_position();
         session
     }
 
@@ -81,6 +97,49 @@ impl CodeSession {
         &self.settings
     }
 
+    /// Replaces the settings, keeping the selections and folds. Wrap data and column counts are
+    /// recomputed, and if the tab width changed, the fold columns of folded lines move along with
+    /// the tabs in their indentation.
+    pub fn set_settings(&mut self, settings: Rc<Settings>) {
+        let old_settings = mem::replace(&mut self.settings, settings);
+        let old_tab_column_count = old_settings.tab_column_count;
+        let new_tab_column_count = self.settings.tab_column_count;
+        // Lines folded by `fold_to_level` fold at a column that follows from the fold level.
+        let old_level_fold_column = old_settings.fold_level * old_settings.indent_column_count();
+        let new_level_fold_column = self.settings.fold_level * self.settings.indent_column_count();
+        if new_tab_column_count != old_tab_column_count
+            || new_level_fold_column != old_level_fold_column
+        {
+            let text = self.document.as_text();
+            let fold_state = self.fold_state.borrow();
+            let mut layout = self.layout.borrow_mut();
+            layout.tab_column_count = new_tab_column_count;
+            for &line_index in fold_state
+                .folding_lines
+                .iter()
+                .chain(&fold_state.folded_lines)
+            {
+                let line = &text.as_lines()[line_index];
+                let fold_column = layout.fold_column[line_index];
+                if fold_column == old_level_fold_column {
+                    layout.fold_column[line_index] = new_level_fold_column;
+                } else if let Some((byte_index, _)) =
+                    line.char_indices().find(|&(byte_index, _)| {
+                        line[..byte_index].column_count_at(0, old_tab_column_count) >= fold_column
+                    })
+                {
+                    layout.fold_column[line_index] =
+                        line[..byte_index].column_count_at(0, new_tab_column_count);
+                }
+            }
+        }
+        let line_count = self.document.as_text().as_lines().len();
+        for line in 0..line_count {
+            self.update_wrap_data(line);
+        }
+        self.update_y();
+    }
+
     pub fn document(&self) -> &CodeDocument {
         &self.document
     }
@@ -97,26 +156,257 @@ impl CodeSession {
         self.wrap_column.get()
     }
 
+    pub fn overtype(&self) -> bool {
+        self.overtype.get()
+    }
+
+    pub fn set_overtype(&self, overtype: bool) {
+        self.overtype.set(overtype);
+    }
+
+    pub fn scroll_offset(&self) -> DVec2 {
+        self.scroll_offset.get()
+    }
+
+    pub fn scroll_to(&self, scroll_offset: DVec2) {
+        let layout = self.layout();
+        self.scroll_offset.set(dvec2(
+            scroll_offset.x.min(layout.width()).max(0.0),
+            scroll_offset.y.min(layout.height()).max(0.0),
+        ));
+    }
+
+    pub fn scroll_by(&self, delta: DVec2) {
+        self.scroll_to(self.scroll_offset.get() + delta);
+    }
+
     pub fn selections(&self) -> Ref<'_, [Selection]> {
         Ref::map(self.selection_state.borrow(), |selection_state| {
             selection_state.selections.as_selections()
         })
     }
 
+    pub fn selection_count(&self) -> usize {
+        self.selection_state.borrow().selections.len()
+    }
+
+    pub fn has_multiple_cursors(&self) -> bool {
+        self.selection_count() > 1
+    }
+
     pub fn last_added_selection_index(&self) -> Option<usize> {
         self.selection_state.borrow().last_added_selection_index
     }
 
+    /// Returns the primary selection, which is the last added one unless another one was made
+    /// primary since.
+    pub fn primary_selection(&self) -> Selection {
+        let selection_state = self.selection_state.borrow();
+        selection_state.selections[selection_state.last_added_selection_index.unwrap_or(0)]
+    }
+
+    pub fn set_primary_selection(&self, index: usize) {
+        let mut selection_state = self.selection_state.borrow_mut();
+        assert!(index < selection_state.selections.len());
+        selection_state.last_added_selection_index = Some(index);
+        drop(selection_state);
+        self.update_cursor_position();
+    }
+
+    pub fn cycle_primary(&self, forward: bool) {
+        let selection_state = self.selection_state.borrow();
+        let selection_count = selection_state.selections.len();
+        let index = selection_state.last_added_selection_index.unwrap_or(0);
+        drop(selection_state);
+        self.set_primary_selection(if forward {
+            (index + 1) % selection_count
+        } else {
+            (index + selection_count - 1) % selection_count
+        });
+    }
+
+    pub fn peer_cursors(&self) -> Vec<(SessionId, Position)> {
+        self.document
+            .cursor_positions()
+            .iter()
+            .filter(|&(&session_id, _)| session_id != self.id)
+            .map(|(&session_id, &position)| (session_id, position))
+            .collect()
+    }
+
     pub fn highlighted_delimiter_positions(&self) -> Ref<'_, HashSet<Position>> {
         Ref::map(self.selection_state.borrow(), |selection_state| {
             &selection_state.highlighted_delimiter_positions
         })
     }
 
+    pub fn word_range_at(&self, position: Position) -> Option<Range<Position>> {
+        let text = self.document.as_text();
+        let line = text.as_lines().get(position.line_index)?;
+        let byte_index = position.byte_index.min(line.len());
+        let is_word_char = |char: char| char.is_word_char(&self.settings.extra_word_chars);
+        let start_byte_index = line[..byte_index]
+            .char_indices()
+            .rev()
+            .take_while(|&(_, char)| is_word_char(char))
+            .last()
+            .map_or(byte_index, |(index, _)| index);
+        let end_byte_index = line[byte_index..]
+            .char_indices()
+            .find(|&(_, char)| !is_word_char(char))
+            .map_or(line.len(), |(index, _)| byte_index + index);
+        if start_byte_index == end_byte_index {
+            return None;
+        }
+        Some(
+            Position {
+                line_index: position.line_index,
+                byte_index: start_byte_index,
+            }..Position {
+                line_index: position.line_index,
+                byte_index: end_byte_index,
+            },
+        )
+    }
+
+    /// Returns the ranges of all non-overlapping occurrences of `needle`, scanning lazily from the
+    /// start of the document. The document text stays borrowed while the iterator is alive.
+    pub fn find_matches<'a>(
+        &'a self,
+        needle: &'a str,
+    ) -> impl Iterator<Item = Range<Position>> + 'a {
+        let text = self.document.as_text();
+        let needle_lines: Vec<&str> = needle.split('\n').collect();
+        let mut position = Position::zero();
+        iter::from_fn(move || {
+            if needle.is_empty() {
+                return None;
+            }
+            let lines = text.as_lines();
+            while position.line_index < lines.len() {
+                let line = &lines[position.line_index];
+                let found = if let [needle_line] = needle_lines[..] {
+                    line[position.byte_index..]
+                        .find(needle_line)
+                        .map(|byte_index| {
+                            let start_byte_index = position.byte_index + byte_index;
+                            Position {
+                                line_index: position.line_index,
+                                byte_index: start_byte_index,
+                            }..Position {
+                                line_index: position.line_index,
+                                byte_index: start_byte_index + needle_line.len(),
+                            }
+                        })
+                } else {
+                    // A multiline needle has to end the first line and start the last one.
+                    let first_needle_line = needle_lines[0];
+                    let last_needle_line = needle_lines[needle_lines.len() - 1];
+                    let last_line_index = position.line_index + needle_lines.len() - 1;
+                    if last_line_index < lines.len()
+                        && line[position.byte_index..].ends_with(first_needle_line)
+                        && needle_lines[1..needle_lines.len() - 1]
+                            .iter()
+                            .zip(&lines[position.line_index + 1..last_line_index])
+                            .all(|(needle_line, line)| needle_line == line)
+                        && lines[last_line_index].starts_with(last_needle_line)
+                    {
+                        Some(
+                            Position {
+                                line_index: position.line_index,
+                                byte_index: line.len() - first_needle_line.len(),
+                            }..Position {
+                                line_index: last_line_index,
+                                byte_index: last_needle_line.len(),
+                            },
+                        )
+                    } else {
+                        None
+                    }
+                };
+                if let Some(range) = found {
+                    position = range.end;
+                    return Some(range);
+                }
+                position = Position {
+                    line_index: position.line_index + 1,
+                    byte_index: 0,
+                };
+            }
+            None
+        })
+    }
+
+    /// Returns the display row and column of the given position, where folded lines take up no
+    /// rows, and wrapped lines take up one row for each wrap.
+    pub fn visual_position(&self, position: Position) -> (usize, usize) {
+        let position = self.clamp_position(position);
+        let layout = self.layout();
+        let fold_state = self.fold_state.borrow();
+        let mut row_index = 0;
+        for (line_index, line) in layout.lines(0, position.line_index).enumerate() {
+            if !fold_state.folded_lines.contains(&line_index) {
+                row_index += line.row_count();
+            }
+        }
+        let (line_row_index, column_index) = layout
+            .line(position.line_index)
+            .logical_to_grid_position(position.byte_index, Affinity::Before);
+        (row_index + line_row_index, column_index)
+    }
+
+    /// Returns the lines that intersect the given vertical range, in the same units as
+    /// `Line::y`. Lines that are folding or unfolding have a scale between 0.1 and 1.0.
+    pub fn laid_out_lines(&self, y_range: Range<f64>) -> impl Iterator<Item = LaidOutLine> {
+        let layout = self.layout();
+        let fold_state = self.fold_state.borrow();
+        let line_start = layout.find_first_line_ending_after_y(y_range.start);
+        let line_end = layout.find_first_line_starting_after_y(y_range.end);
+        let lines: Vec<_> = layout
+            .lines(line_start, line_end)
+            .enumerate()
+            .map(|(index, line)| {
+                let line_index = line_start + index;
+                LaidOutLine {
+                    line_index,
+                    y: line.y(),
+                    height: line.height(),
+                    scale: line.scale(),
+                    fold_column_index: line.fold(),
+                    wrap_data: line.wrap_data.cloned(),
+                    is_folded: fold_state.folded_lines.contains(&line_index),
+                }
+            })
+            .collect();
+        lines.into_iter()
+    }
+
+    /// Returns what the gutter needs to draw the number of the given line. While the line is
+    /// folding or unfolding, `scale` and `height` are the current values of the animation.
+    pub fn line_gutter_info(&self, line_index: usize) -> LineGutterInfo {
+        let layout = self.layout();
+        let fold_state = self.fold_state.borrow();
+        let is_hidden = |line_index| {
+            fold_state.folding_lines.contains(&line_index)
+                || fold_state.folded_lines.contains(&line_index)
+        };
+        let line = layout.line(line_index);
+        LineGutterInfo {
+            y: line.y(),
+            height: line.height(),
+            scale: line.scale(),
+            is_folded: fold_state.folded_lines.contains(&line_index),
+            fold_header: !is_hidden(line_index) && is_hidden(line_index + 1),
+        }
+    }
+
     pub fn set_wrap_column(&self, wrap_column: Option<usize>) {
         if self.wrap_column.get() == wrap_column {
             return;
         }
+        if wrap_column.is_some() {
+            self.last_wrap_column.set(wrap_column);
+        }
         self.wrap_column.set(wrap_column);
         let line_count = self.document.as_text().as_lines().len();
         for line in 0..line_count {
@@ -125,19 +415,42 @@ impl CodeSession {
         self.update_y();
     }
 
+    /// Turns wrapping off if it is on, and otherwise wraps at the column that was last used, or at
+    /// `default_column` if wrapping was never turned on.
+    pub fn toggle_wrap(&self, default_column: usize) {
+        if self.wrap_column.get().is_some() {
+            self.set_wrap_column(None);
+        } else {
+            self.set_wrap_column(Some(self.last_wrap_column.get().unwrap_or(default_column)));
+        }
+    }
+
+    /// Returns the indent level of the given line as displayed, with hard tabs advancing to the
+    /// next tab stop. Lines without any non-whitespace take their level from the surrounding
+    /// lines.
+    pub fn line_indent_level(&self, line_index: usize) -> usize {
+        let layout = self.layout();
+        let line = layout.line(line_index);
+        let indent_column_count = match line.text().indent() {
+            Some(indent) => indent.column_count_at(0, self.settings.tab_column_count),
+            None => line.indent_column_count(),
+        };
+        indent_column_count / self.settings.indent_column_count()
+    }
+
     pub fn fold(&self) {
+        self.fold_to_level(self.settings.fold_level);
+    }
+
+    pub fn fold_to_level(&self, fold_level: usize) {
         let mut fold_state = self.fold_state.borrow_mut();
+        fold_state.animation_finished = false;
         let line_count = self.document().as_text().as_lines().len();
         for line_index in 0..line_count {
-            let layout = self.layout();
-            let line = layout.line(line_index);
-            let indent_level = line.indent_column_count() / self.settings.tab_column_count;
-            drop(layout);
-            if indent_level >= self.settings.fold_level
-                && !fold_state.folded_lines.contains(&line_index)
-            {
+            let indent_level = self.line_indent_level(line_index);
+            if indent_level >= fold_level && !fold_state.folded_lines.contains(&line_index) {
                 self.layout.borrow_mut().fold_column[line_index] =
-                    self.settings.fold_level * self.settings.tab_column_count;
+                    fold_level * self.settings.indent_column_count();
                 fold_state.unfolding_lines.remove(&line_index);
                 fold_state.folding_lines.insert(line_index);
             }
@@ -146,6 +459,7 @@ impl CodeSession {
 
     pub fn unfold(&self) {
         let fold_state = &mut *self.fold_state.borrow_mut();
+        fold_state.animation_finished = false;
         for line in fold_state.folding_lines.drain() {
             fold_state.unfolding_lines.insert(line);
         }
@@ -154,6 +468,96 @@ impl CodeSession {
         }
     }
 
+    pub fn is_line_folded(&self, line_index: usize) -> bool {
+        self.fold_state.borrow().folded_lines.contains(&line_index)
+    }
+
+    pub fn folded_range_containing(&self, line_index: usize) -> Option<Range<usize>> {
+        let fold_state = self.fold_state.borrow();
+        if !fold_state.folded_lines.contains(&line_index) {
+            return None;
+        }
+        let mut start = line_index;
+        while start > 0 && fold_state.folded_lines.contains(&(start - 1)) {
+            start -= 1;
+        }
+        let mut end = line_index + 1;
+        while fold_state.folded_lines.contains(&end) {
+            end += 1;
+        }
+        Some(start..end)
+    }
+
+    /// Returns the text to draw for the fold that follows the given line, which is the line's
+    /// trimmed content followed by the ellipsis from the settings, or `None` if the next line is
+    /// not folded.
+    pub fn fold_placeholder(&self, line_index: usize) -> Option<String> {
+        let fold_state = self.fold_state.borrow();
+        let is_folded = |line_index| {
+            fold_state.folding_lines.contains(&line_index)
+                || fold_state.folded_lines.contains(&line_index)
+        };
+        if is_folded(line_index) || !is_folded(line_index + 1) {
+            return None;
+        }
+        let text = self.document.as_text();
+        let line = text.as_lines().get(line_index)?;
+        Some(format!("{}{}", line.trim(), self.settings.fold_ellipsis))
+    }
+
+    pub fn toggle_fold_at_cursor(&self) {
+        let selection_state = self.selection_state.borrow();
+        let line_index = selection_state.selections
+            [selection_state.last_added_selection_index.unwrap_or(0)]
+        .cursor
+        .position
+        .line_index;
+        drop(selection_state);
+        let layout = self.layout();
+        let indent_column_count = layout.line(line_index).indent_column_count();
+        let line_count = layout.as_text().as_lines().len();
+        let block_end = (line_index + 1..line_count)
+            .find(|&line_index| layout.line(line_index).indent_column_count() <= indent_column_count)
+            .unwrap_or(line_count);
+        drop(layout);
+        let block = line_index + 1..block_end;
+        if block.is_empty() {
+            return;
+        }
+        let fold_state = &mut *self.fold_state.borrow_mut();
+        fold_state.animation_finished = false;
+        if block.clone().any(|line_index| {
+            fold_state.folding_lines.contains(&line_index)
+                || fold_state.folded_lines.contains(&line_index)
+        }) {
+            for line_index in block {
+                fold_state.folding_lines.remove(&line_index);
+                fold_state.folded_lines.remove(&line_index);
+                fold_state.unfolding_lines.insert(line_index);
+            }
+        } else {
+            let mut layout = self.layout.borrow_mut();
+            for line_index in block {
+                layout.fold_column[line_index] = indent_column_count;
+                fold_state.unfolding_lines.remove(&line_index);
+                fold_state.folding_lines.insert(line_index);
+            }
+        }
+    }
+
+    /// Returns `true` while lines are still folding or unfolding, that is, while `update_folds`
+    /// still has work to do.
+    pub fn is_fold_animating(&self) -> bool {
+        let fold_state = self.fold_state.borrow();
+        !fold_state.folding_lines.is_empty() || !fold_state.unfolding_lines.is_empty()
+    }
+
+    /// Returns `true` once after the call to `update_folds` that finished the fold animation, so
+    /// the caller knows it can stop requesting frames.
+    pub fn take_fold_animation_finished(&self) -> bool {
+        mem::take(&mut self.fold_state.borrow_mut().animation_finished)
+    }
+
     pub fn update_folds(&self) -> bool {
         let mut fold_state_ref = self.fold_state.borrow_mut();
         if fold_state_ref.folding_lines.is_empty() && fold_state_ref.unfolding_lines.is_empty() {
@@ -162,6 +566,11 @@ impl CodeSession {
         let mut layout = self.layout.borrow_mut();
         let mut new_folding_lines = HashSet::new();
         let fold_state = &mut *fold_state_ref;
+        // Edits don't move the fold state along, so drop any lines that no longer exist.
+        let line_count = layout.scale.len();
+        fold_state.folding_lines.retain(|&line| line < line_count);
+        fold_state.folded_lines.retain(|&line| line < line_count);
+        fold_state.unfolding_lines.retain(|&line| line < line_count);
         for &line in &fold_state.folding_lines {
             layout.scale[line] *= 0.9;
             if layout.scale[line] < 0.1 + 0.001 {
@@ -185,6 +594,9 @@ impl CodeSession {
             layout.y.truncate(line + 1);
         }
         fold_state_ref.unfolding_lines = new_unfolding_lines;
+        if fold_state_ref.folding_lines.is_empty() && fold_state_ref.unfolding_lines.is_empty() {
+            fold_state_ref.animation_finished = true;
+        }
         drop(layout);
         drop(fold_state_ref);
         self.update_y();
@@ -201,15 +613,17 @@ impl CodeSession {
             }),
             self.document().as_text().as_lines(),
             mode,
-            &self.settings.word_separators,
+            &self.settings.extra_word_chars,
         );
         let mut selection_state = self.selection_state.borrow_mut();
         selection_state.mode = mode;
         selection_state.selections.set_selection(selection);
         selection_state.last_added_selection_index = Some(0);
         selection_state.injected_char_stack.clear();
+        selection_state.snippet_stops.clear();
         drop(selection_state);
         self.update_highlighted_delimiter_positions();
+        self.update_cursor_position();
         if let NewGroup::Yes = new_group{
             self.document().force_new_group();
         }
@@ -239,7 +653,7 @@ impl CodeSession {
             }),
             self.document().as_text().as_lines(),
             mode,
-            &self.settings.word_separators,
+            &self.settings.extra_word_chars,
         );
         let mut selection_state = self.selection_state.borrow_mut();
         selection_state.mode = mode;
@@ -248,10 +662,85 @@ impl CodeSession {
         selection_state.injected_char_stack.clear();
         drop(selection_state);
         self.update_highlighted_delimiter_positions();
+        self.update_cursor_position();
+        self.document().force_new_group();
+    }
+
+    /// Adds a selection for every occurrence of the text of the primary selection, or of the
+    /// word under the primary cursor if the primary selection is empty, in which case the word
+    /// itself gets selected too. Occurrences that overlap an existing selection are skipped. The
+    /// last occurrence becomes the primary selection.
+    pub fn select_all_occurrences(&self) {
+        let primary_selection = self.primary_selection();
+        let range = if primary_selection.is_empty() {
+            match self.word_range_at(primary_selection.cursor.position) {
+                Some(range) => range,
+                None => return,
+            }
+        } else {
+            primary_selection.start()..primary_selection.end()
+        };
+        let needle = self.text_in_range(range.clone()).to_string();
+        let ranges = self.find_matches(&needle).collect::<Vec<_>>();
+        let selection = |range: Range<Position>| Selection {
+            cursor: Cursor {
+                position: range.end,
+                affinity: Affinity::Before,
+                preferred_column_index: None,
+            },
+            anchor: range.start,
+        };
+        let mut selection_state = self.selection_state.borrow_mut();
+        let mut last_added_selection_index =
+            selection_state.last_added_selection_index.unwrap_or(0);
+        if primary_selection.is_empty() {
+            last_added_selection_index = selection_state
+                .selections
+                .update_selection(last_added_selection_index, |_| selection(range));
+        }
+        for range in ranges {
+            let selection = selection(range);
+            if selection_state
+                .selections
+                .iter()
+                .any(|other_selection| other_selection.overlaps(selection))
+            {
+                continue;
+            }
+            last_added_selection_index = selection_state.selections.add_selection(selection);
+        }
+        selection_state.last_added_selection_index = Some(last_added_selection_index);
+        selection_state.injected_char_stack.clear();
+        drop(selection_state);
+        self.update_highlighted_delimiter_positions();
+        self.update_cursor_position();
         self.document().force_new_group();
     }
 
+    pub fn collapse_to_primary(&self) {
+        let cursor = self.primary_selection().cursor;
+        let mut selection_state = self.selection_state.borrow_mut();
+        selection_state.selections.set_selection(Selection::from(cursor));
+        selection_state.last_added_selection_index = Some(0);
+        selection_state.injected_char_stack.clear();
+        drop(selection_state);
+        self.update_highlighted_delimiter_positions();
+        self.update_cursor_position();
+    }
+
     pub fn move_to(&self, position: Position, affinity: Affinity, new_group:NewGroup) {
+        let mode = self.selection_state.borrow().mode;
+        if mode == SelectionMode::Word || mode == SelectionMode::Line {
+            if mode == SelectionMode::Word {
+                self.extend_selection_to_word(position);
+            } else {
+                self.extend_selection_to_line(position);
+            }
+            if let NewGroup::Yes = new_group {
+                self.document().force_new_group();
+            }
+            return;
+        }
         let mut selection_state = self.selection_state.borrow_mut();
         let last_added_selection_index = selection_state.last_added_selection_index.unwrap();
         let mode = selection_state.mode;
@@ -267,27 +756,122 @@ impl CodeSession {
                         }),
                         self.document.as_text().as_lines(),
                         mode,
-                        &self.settings.word_separators,
+                        &self.settings.extra_word_chars,
                     )
                 }),
         );
         selection_state.injected_char_stack.clear();
         drop(selection_state);
         self.update_highlighted_delimiter_positions();
+        self.update_cursor_position();
         if let NewGroup::Yes = new_group{
             self.document().force_new_group();
         }
     }
 
+    /// Extends the last added selection to whole words, for dragging after a double click. The
+    /// cursor moves to the far boundary of the word at `position`, and the anchor moves to the
+    /// edge of its own word that keeps that word selected.
+    pub fn extend_selection_to_word(&self, position: Position) {
+        let position = self.clamp_position(position);
+        let mut selection_state = self.selection_state.borrow_mut();
+        let last_added_selection_index = selection_state.last_added_selection_index.unwrap();
+        let anchor = selection_state.selections[last_added_selection_index].anchor;
+        let anchor_range = self.word_range_at(anchor).unwrap_or(anchor..anchor);
+        let cursor_range = self.word_range_at(position).unwrap_or(position..position);
+        let selection = if position < anchor_range.start {
+            Selection {
+                cursor: Cursor {
+                    position: cursor_range.start,
+                    affinity: Affinity::After,
+                    preferred_column_index: None,
+                },
+                anchor: anchor_range.end,
+            }
+        } else {
+            Selection {
+                cursor: Cursor {
+                    position: cursor_range.end.max(anchor_range.end),
+                    affinity: Affinity::Before,
+                    preferred_column_index: None,
+                },
+                anchor: anchor_range.start,
+            }
+        };
+        selection_state.last_added_selection_index = Some(
+            selection_state
+                .selections
+                .update_selection(last_added_selection_index, |_| selection),
+        );
+        selection_state.injected_char_stack.clear();
+        drop(selection_state);
+        self.update_highlighted_delimiter_positions();
+        self.update_cursor_position();
+    }
+
+    /// Extends the last added selection to whole lines, for dragging after a triple click. The
+    /// selection covers every line from the anchor's line to the line of `position`.
+    pub fn extend_selection_to_line(&self, position: Position) {
+        let position = self.clamp_position(position);
+        let text = self.document.as_text();
+        let lines = text.as_lines();
+        let mut selection_state = self.selection_state.borrow_mut();
+        let last_added_selection_index = selection_state.last_added_selection_index.unwrap();
+        let anchor_line_index = selection_state.selections[last_added_selection_index]
+            .anchor
+            .line_index;
+        let selection = if position.line_index < anchor_line_index {
+            Selection {
+                cursor: Cursor {
+                    position: Position {
+                        line_index: position.line_index,
+                        byte_index: 0,
+                    },
+                    affinity: Affinity::After,
+                    preferred_column_index: None,
+                },
+                anchor: Position {
+                    line_index: anchor_line_index,
+                    byte_index: lines[anchor_line_index].len(),
+                },
+            }
+        } else {
+            Selection {
+                cursor: Cursor {
+                    position: Position {
+                        line_index: position.line_index,
+                        byte_index: lines[position.line_index].len(),
+                    },
+                    affinity: Affinity::Before,
+                    preferred_column_index: None,
+                },
+                anchor: Position {
+                    line_index: anchor_line_index,
+                    byte_index: 0,
+                },
+            }
+        };
+        drop(text);
+        selection_state.last_added_selection_index = Some(
+            selection_state
+                .selections
+                .update_selection(last_added_selection_index, |_| selection),
+        );
+        selection_state.injected_char_stack.clear();
+        drop(selection_state);
+        self.update_highlighted_delimiter_positions();
+        self.update_cursor_position();
+    }
+
     pub fn move_left(&self, reset_anchor: bool) {
         self.modify_selections(reset_anchor, |selection, layout| {
-            selection.update_cursor(|cursor| cursor.move_left(layout.as_text().as_lines()))
+            selection.update_cursor(|cursor| cursor.move_left(layout))
         });
     }
 
     pub fn move_right(&self, reset_anchor: bool) {
         self.modify_selections(reset_anchor, |selection, layout| {
-            selection.update_cursor(|cursor| cursor.move_right(layout.as_text().as_lines()))
+            selection.update_cursor(|cursor| cursor.move_right(layout))
         });
     }
 
@@ -315,15 +899,45 @@ impl CodeSession {
         });
     }
 
+    pub fn move_to_next_paragraph(&self, reset_anchor: bool) {
+        self.modify_selections(reset_anchor, |selection, layout| {
+            selection.update_cursor(|cursor| {
+                cursor.move_to_next_paragraph(layout.as_text().as_lines())
+            })
+        });
+    }
+
+    pub fn move_to_prev_paragraph(&self, reset_anchor: bool) {
+        self.modify_selections(reset_anchor, |selection, layout| {
+            selection.update_cursor(|cursor| {
+                cursor.move_to_prev_paragraph(layout.as_text().as_lines())
+            })
+        });
+    }
+
+    pub fn move_to_matching_bracket(&self, reset_anchor: bool) {
+        self.modify_selections(reset_anchor, |selection, layout| {
+            selection.update_cursor(|cursor| {
+                cursor.move_to_matching_bracket(
+                    layout.as_text().as_lines(),
+                    &layout.document_layout.tokens,
+                )
+            })
+        });
+    }
+
     pub fn insert(&self, text: Text) {
 
         let mut edit_kind = EditKind::Insert;
         let mut inject_char = None;
         let mut uninject_char = None;
+        let overtype = self.overtype.get() && text.to_single_char().is_some();
         if let Some(char) = text.to_single_char() {
             let mut selection_state = self.selection_state.borrow_mut();
             if char == ' ' {
                 edit_kind = EditKind::InsertSpace;
+            } else if !self.settings.auto_close_delimiters || overtype {
+                // Delimiter injection is disabled, so just insert the character.
             } else if char == '"' || char.is_opening_delimiter() {
                 if selection_state
                     .selections
@@ -369,6 +983,7 @@ impl CodeSession {
             }
             drop(selection_state);
         }
+        let mut virtual_column_counts = self.virtual_column_counts().into_iter();
         self.document.edit_selections(
             self.id,
             edit_kind,
@@ -377,6 +992,26 @@ impl CodeSession {
             |mut editor, position, length| {
                 let mut position = position;
                 let mut length = length;
+                let virtual_column_count = virtual_column_counts.next().unwrap();
+                if virtual_column_count > 0 {
+                    // The cursor is in virtual space, so pad the line with spaces up to the
+                    // cursor before inserting.
+                    let padding: Text = " ".repeat(virtual_column_count).into();
+                    editor.apply_edit(Edit {
+                        change: Change::Insert(position, padding.clone()),
+                        drift: Drift::Before,
+                    });
+                    position += padding.length();
+                }
+                if overtype && length == Length::zero() {
+                    // In overtype mode, an empty selection overwrites the grapheme after the
+                    // cursor, if any.
+                    length.byte_count = editor.as_text().as_lines()[position.line_index]
+                        [position.byte_index..]
+                        .graphemes()
+                        .next()
+                        .map_or(0, |grapheme| grapheme.len());
+                }
                 if inject_char.is_none() {
                     // Only delete the selection if we are NOT injecting a character. This is for the
                     // use case where we have selected `abc` and want to enclose it like: `{abc}`.
@@ -419,6 +1054,200 @@ impl CodeSession {
         );
     }
 
+    pub fn surround_selection(&self, open: &str, close: &str) {
+        self.selection_state
+            .borrow_mut()
+            .injected_char_stack
+            .clear();
+        let open = Text::from(open);
+        let close = Text::from(close);
+        self.document.edit_selections(
+            self.id,
+            EditKind::Other,
+            &self.selection_state.borrow().selections,
+            &self.settings,
+            |mut editor, position, length| {
+                if length == Length::zero() {
+                    return;
+                }
+                // The opening text is inserted with Drift::Before and the closing text with
+                // Drift::After, so that the selection moves along with the text it encloses.
+                editor.apply_edit(Edit {
+                    change: Change::Insert(position, open.clone()),
+                    drift: Drift::Before,
+                });
+                editor.apply_edit(Edit {
+                    change: Change::Insert(position + open.length() + length, close.clone()),
+                    drift: Drift::After,
+                });
+            },
+        );
+    }
+
+    /// Inserts `snippet` in place of the primary selection. The snippet may contain the tab stops
+    /// `$1`, `$2`, ... and `$0`, and `\$` for a literal dollar sign. Afterwards there is one
+    /// cursor for each occurrence of the first tab stop, and `next_snippet_stop` and
+    /// `prev_snippet_stop` move through the others, with `$0` (or the end of the snippet) last.
+    pub fn insert_snippet(&self, snippet: &str) {
+        // Bring the selections up to date, so that the snippet goes where the cursor is now.
+        self.receive_edits();
+        self.collapse_to_primary();
+        let selection = self.primary_selection();
+        let start = selection.start();
+        let indent = self.document.as_text().as_lines()[start.line_index]
+            .indent()
+            .unwrap_or("")
+            .to_string();
+        let (text, stops) = parse_snippet(snippet, &indent);
+        self.document.edit(
+            self.id,
+            EditKind::Other,
+            &self.selection_state.borrow().selections,
+            |mut editor| {
+                editor.apply_edit(Edit {
+                    change: Change::Delete(start, selection.length()),
+                    drift: Drift::Before,
+                });
+                editor.apply_edit(Edit {
+                    change: Change::Insert(start, text),
+                    drift: Drift::Before,
+                });
+            },
+        );
+        self.receive_edits();
+        self.selection_state.borrow_mut().snippet_stops = stops
+            .into_iter()
+            .map(|lengths| lengths.into_iter().map(|length| start + length).collect())
+            .collect();
+        self.select_snippet_stop(0);
+    }
+
+    pub fn next_snippet_stop(&self) -> bool {
+        let selection_state = self.selection_state.borrow();
+        if selection_state.snippet_stops.is_empty() {
+            return false;
+        }
+        let index = selection_state.snippet_stop_index + 1;
+        drop(selection_state);
+        self.select_snippet_stop(index);
+        true
+    }
+
+    pub fn prev_snippet_stop(&self) -> bool {
+        let selection_state = self.selection_state.borrow();
+        if selection_state.snippet_stops.is_empty() || selection_state.snippet_stop_index == 0 {
+            return false;
+        }
+        let index = selection_state.snippet_stop_index - 1;
+        drop(selection_state);
+        self.select_snippet_stop(index);
+        true
+    }
+
+    /// Replaces `replace_range` (usually the partial word before the cursor) with `text`, leaving
+    /// a single cursor after it. The edit counts as typing, so it is undone together with the
+    /// typing around it.
+    pub fn commit_completion(&self, replace_range: Range<Position>, text: Text) {
+        self.receive_edits();
+        self.collapse_to_primary();
+        let start = self.clamp_position(replace_range.start);
+        let end = self.clamp_position(replace_range.end).max(start);
+        let length = text.length();
+        self.document.edit(
+            self.id,
+            EditKind::Insert,
+            &self.selection_state.borrow().selections,
+            |mut editor| {
+                editor.apply_edit(Edit {
+                    change: Change::Delete(start, end - start),
+                    drift: Drift::Before,
+                });
+                editor.apply_edit(Edit {
+                    change: Change::Insert(start, text),
+                    drift: Drift::Before,
+                });
+            },
+        );
+        self.receive_edits();
+        let mut selection_state = self.selection_state.borrow_mut();
+        selection_state.selections.set_selection(Selection::from(Cursor {
+            position: start + length,
+            affinity: Affinity::Before,
+            preferred_column_index: None,
+        }));
+        selection_state.last_added_selection_index = Some(0);
+        drop(selection_state);
+        self.update_highlighted_delimiter_positions();
+        self.update_cursor_position();
+    }
+
+    /// Returns the range of the text that is being composed with an input method, if any.
+    pub fn composition_range(&self) -> Option<Range<Position>> {
+        self.selection_state.borrow().composition_range.clone()
+    }
+
+    /// Sets the text that is being composed with an input method, replacing the previous
+    /// composition if there is one. The text is a provisional edit that stays out of the undo
+    /// history until `commit_composition` is called.
+    pub fn set_composition(&self, text: Text) {
+        self.receive_edits();
+        let start = match self.remove_composition() {
+            Some((start, _)) => start,
+            None => {
+                self.collapse_to_primary();
+                let selection = self.primary_selection();
+                if !selection.is_empty() {
+                    self.document.edit(
+                        self.id,
+                        EditKind::Other,
+                        &self.selection_state.borrow().selections,
+                        |mut editor| {
+                            editor.apply_edit(Edit {
+                                change: Change::Delete(selection.start(), selection.length()),
+                                drift: Drift::Before,
+                            });
+                        },
+                    );
+                    self.receive_edits();
+                }
+                selection.start()
+            }
+        };
+        let length = text.length();
+        if !text.is_empty() {
+            self.document.apply_provisional_edits(
+                self.id,
+                vec![Edit {
+                    change: Change::Insert(start, text),
+                    drift: Drift::Before,
+                }],
+            );
+            self.receive_edits();
+        }
+        self.selection_state.borrow_mut().composition_range = Some(start..start + length);
+    }
+
+    /// Replaces the composed text with a regular edit, so that it can be undone. Returns `false`
+    /// if there is no composition.
+    pub fn commit_composition(&self) -> bool {
+        self.receive_edits();
+        match self.remove_composition() {
+            Some((_, text)) => {
+                if !text.is_empty() {
+                    self.insert(text);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Removes the composed text. Returns `false` if there is no composition.
+    pub fn cancel_composition(&self) -> bool {
+        self.receive_edits();
+        self.remove_composition().is_some()
+    }
+
     pub fn paste(&self, text: Text) {
         self.document.edit_selections(
             self.id,
@@ -438,6 +1267,32 @@ impl CodeSession {
         );
     }
     
+    pub fn paste_segments(&self, segments: &[String]) {
+        if segments.len() != self.selection_state.borrow().selections.len() {
+            // The segments can't be distributed one per selection, so paste all of them at every
+            // selection instead.
+            self.paste(segments.concat().into());
+            return;
+        }
+        let mut segments = segments.iter();
+        self.document.edit_selections(
+            self.id,
+            EditKind::Other,
+            &self.selection_state.borrow().selections,
+            &self.settings,
+            |mut editor, position, length| {
+                editor.apply_edit(Edit {
+                    change: Change::Delete(position, length),
+                    drift: Drift::Before,
+                });
+                editor.apply_edit(Edit {
+                    change: Change::Insert(position, segments.next().unwrap().into()),
+                    drift: Drift::Before,
+                });
+            },
+        );
+    }
+
     pub fn paste_grouped(&self, text: Text, group:u64) {
         self.document.edit_selections(
             self.id,
@@ -456,7 +1311,27 @@ impl CodeSession {
             },
         );
     }
-    
+    
+    pub fn apply_external_edit(&self, edits: &[Edit], kind: EditKind) {
+        if edits.is_empty() {
+            return;
+        }
+        self.selection_state
+            .borrow_mut()
+            .injected_char_stack
+            .clear();
+        self.document.edit(
+            self.id,
+            kind,
+            &self.selection_state.borrow().selections,
+            |mut editor| {
+                for edit in edits {
+                    editor.apply_edit(edit.clone());
+                }
+            },
+        );
+    }
+
     pub fn enter(&self) {
         self.selection_state
             .borrow_mut()
@@ -469,23 +1344,57 @@ impl CodeSession {
             &self.settings,
             |mut editor, position, length| {
                 let line = &editor.as_text().as_lines()[position.line_index];
+                let indent_len = line.indent().unwrap_or("").len();
+                let comment_prefix = self
+                    .settings
+                    .line_comment
+                    .as_deref()
+                    .filter(|_| length == Length::zero())
+                    .and_then(|line_comment| line_comment_prefix(line, line_comment))
+                    .filter(|comment_prefix| {
+                        position.byte_index >= indent_len + comment_prefix.len()
+                    })
+                    .map(|comment_prefix| comment_prefix.to_string());
+                if let Some(comment_prefix) = &comment_prefix {
+                    if line[indent_len + comment_prefix.len()..].trim().is_empty() {
+                        // The comment on this line is empty, so stop continuing it by clearing
+                        // the comment prefix instead of inserting a newline.
+                        let byte_count = line.len() - indent_len;
+                        editor.apply_edit(Edit {
+                            change: Change::Delete(
+                                Position {
+                                    line_index: position.line_index,
+                                    byte_index: indent_len,
+                                },
+                                Length {
+                                    line_count: 0,
+                                    byte_count,
+                                },
+                            ),
+                            drift: Drift::Before,
+                        });
+                        return;
+                    }
+                }
                 let delete_whitespace = !line.is_empty()
                     && line[..position.byte_index]
                         .chars()
                         .all(|char| char.is_whitespace());
-                let inject_newline = line[..position.byte_index]
-                    .chars()
-                    .rev()
-                    .find_map(|char| {
-                        if char.is_opening_delimiter() {
-                            return Some(true);
-                        }
-                        if char.is_closing_delimiter() {
-                            return Some(false);
-                        }
-                        None
-                    })
-                    .unwrap_or(false)
+                let inject_newline = self.settings.auto_close_delimiters
+                    && comment_prefix.is_none()
+                    && line[..position.byte_index]
+                        .chars()
+                        .rev()
+                        .find_map(|char| {
+                            if char.is_opening_delimiter() {
+                                return Some(true);
+                            }
+                            if char.is_closing_delimiter() {
+                                return Some(false);
+                            }
+                            None
+                        })
+                        .unwrap_or(false)
                     && line[position.byte_index..]
                         .chars()
                         .find_map(|char| {
@@ -525,6 +1434,14 @@ impl CodeSession {
                 });
                 position.line_index += 1;
                 position.byte_index = 0;
+                if let Some(comment_prefix) = comment_prefix {
+                    // Continue the line comment on the new line. Its indentation is fixed up by
+                    // the autoindent pass.
+                    editor.apply_edit(Edit {
+                        change: Change::Insert(position, comment_prefix.into()),
+                        drift: Drift::Before,
+                    });
+                }
                 if inject_newline {
                     editor.apply_edit(Edit {
                         change: Change::Insert(position, Text::newline()),
@@ -592,8 +1509,11 @@ impl CodeSession {
                     } else {
                         // There is at least one non-whitespace character before the cursor on the
                         // current line, so delete forward by a single grapheme.
-                        let byte_count =
-                            lines[position.line_index].graphemes().next().unwrap().len();
+                        let byte_count = lines[position.line_index][position.byte_index..]
+                            .graphemes()
+                            .next()
+                            .unwrap()
+                            .len();
                         editor.apply_edit(Edit {
                             change: Change::Delete(
                                 position,
@@ -697,7 +1617,7 @@ impl CodeSession {
                     } else {
                         // There is at least one non-whitespace character before the cursor on the
                         // current line, so delete backwards by a single grapheme.
-                        let byte_count = lines[position.line_index]
+                        let byte_count = lines[position.line_index][..position.byte_index]
                             .graphemes()
                             .next_back()
                             .unwrap()
@@ -728,24 +1648,119 @@ impl CodeSession {
     }
 
     pub fn indent(&self) {
+        let step = self.settings.indent_column_count();
+        self.reindent_lines(|indent_column_count| {
+            indent_column_count + step - indent_column_count % step
+        });
+    }
+
+    pub fn outdent(&self) {
+        let step = self.settings.indent_column_count();
+        self.reindent_lines(|indent_column_count| {
+            indent_column_count
+                - indent_column_count.min((indent_column_count + step - 1) % step + 1)
+        });
+    }
+
+    pub fn set_indent_level(&self, indent_level: usize) {
+        let indent_column_count = indent_level * self.settings.indent_column_count();
+        self.reindent_lines(|_| indent_column_count);
+    }
+
+    pub fn duplicate_selection_content_inline(&self) {
+        let selections: Vec<_> = self
+            .selection_state
+            .borrow()
+            .selections
+            .iter()
+            .copied()
+            .collect();
+        self.document.edit(
+            self.id,
+            EditKind::Other,
+            &self.selection_state.borrow().selections,
+            |mut editor| {
+                // Insert each copy in front of its original, so that the original moves past it
+                // and ends up selected. Selections are handled back to front so that the
+                // positions of the ones before them stay valid.
+                let mut prev_line_index = None;
+                for selection in selections.iter().rev() {
+                    let (position, text) = if selection.is_empty() {
+                        let line_index = selection.cursor.position.line_index;
+                        if prev_line_index == Some(line_index) {
+                            continue;
+                        }
+                        prev_line_index = Some(line_index);
+                        let mut line = editor.as_text().as_lines()[line_index].clone();
+                        line.push('\n');
+                        (
+                            Position {
+                                line_index,
+                                byte_index: 0,
+                            },
+                            line.into(),
+                        )
+                    } else {
+                        (
+                            selection.start(),
+                            editor
+                                .as_text()
+                                .slice(selection.start(), selection.length()),
+                        )
+                    };
+                    editor.apply_edit(Edit {
+                        change: Change::Insert(position, text),
+                        drift: Drift::Before,
+                    });
+                }
+            },
+        );
+    }
+
+    pub fn align_cursors_on(&self, delimiter: char) {
+        let delimiter_column_index = |line: &str| {
+            line.find(delimiter).map(|byte_index| {
+                line[..byte_index].column_count_at(0, self.settings.tab_column_count)
+            })
+        };
+        let text = self.document.as_text();
+        let column_indices: Vec<_> = self
+            .selection_state
+            .borrow()
+            .selections
+            .iter()
+            .flat_map(|selection| selection.line_range())
+            .filter_map(|line_index| delimiter_column_index(&text.as_lines()[line_index]))
+            .collect();
+        drop(text);
+        let max_column_index = match column_indices.iter().max() {
+            Some(&max_column_index) => max_column_index,
+            None => return,
+        };
+        if column_indices
+            .iter()
+            .all(|&column_index| column_index == max_column_index)
+        {
+            return;
+        }
         self.document.edit_linewise(
             self.id,
             EditKind::Other,
             &self.selection_state.borrow().selections,
             |mut editor, line_index| {
-                let indent_column_count = editor.as_text().as_lines()[line_index]
-                    .indent()
-                    .unwrap_or("")
-                    .len();
-                let column_count = self.settings.tab_column_count
-                    - indent_column_count % self.settings.tab_column_count;
+                let line = &editor.as_text().as_lines()[line_index];
+                let column_index = match delimiter_column_index(line) {
+                    Some(column_index) if column_index < max_column_index => column_index,
+                    _ => return,
+                };
+                let byte_index = line.find(delimiter).unwrap();
                 editor.apply_edit(Edit {
                     change: Change::Insert(
                         Position {
                             line_index,
-                            byte_index: indent_column_count,
+                            byte_index,
                         },
-                        iter::repeat(' ').take(column_count).collect(),
+                        " ".repeat(max_column_index - column_index).into(),
                     ),
                     drift: Drift::Before,
                 });
@@ -753,32 +1768,92 @@ impl CodeSession {
         );
     }
 
-    pub fn outdent(&self) {
-        self.document.edit_linewise(
+    pub fn merged_selections(&self) -> Vec<Selection> {
+        self.selection_state
+            .borrow()
+            .selections
+            .iter()
+            .copied()
+            .merge(|selection_0, selection_1| {
+                selection_0
+                    .merge_with(selection_1)
+                    .ok_or((selection_0, selection_1))
+            })
+            .collect()
+    }
+
+    pub fn reflow_paragraph(&self, width: usize) {
+        let selection_state = self.selection_state.borrow();
+        let line_index = selection_state.selections
+            [selection_state.last_added_selection_index.unwrap_or(0)]
+        .cursor
+        .position
+        .line_index;
+        drop(selection_state);
+        let text = self.document.as_text();
+        let lines = text.as_lines();
+        let line_comment = self.settings.line_comment.as_deref();
+        let (prefix, key) = paragraph_prefix(&lines[line_index], line_comment);
+        if lines[line_index][prefix.len()..].trim().is_empty() {
+            return;
+        }
+        let is_in_paragraph = |line: &str| {
+            let (prefix, other_key) = paragraph_prefix(line, line_comment);
+            other_key == key && !line[prefix.len()..].trim().is_empty()
+        };
+        let mut start = line_index;
+        while start > 0 && is_in_paragraph(&lines[start - 1]) {
+            start -= 1;
+        }
+        let mut end = line_index + 1;
+        while end < lines.len() && is_in_paragraph(&lines[end]) {
+            end += 1;
+        }
+        let mut new_lines = Vec::new();
+        let mut new_line = String::new();
+        for word in lines[start..end].iter().flat_map(|line| {
+            line[paragraph_prefix(line, line_comment).0.len()..].split_whitespace()
+        }) {
+            if new_line.is_empty() {
+                new_line.push_str(prefix);
+                new_line.push_str(word);
+            } else if new_line.column_count_at(0, self.settings.tab_column_count)
+                + 1
+                + word.column_count()
+                <= width
+            {
+                new_line.push(' ');
+                new_line.push_str(word);
+            } else {
+                new_lines.push(mem::take(&mut new_line));
+                new_line.push_str(prefix);
+                new_line.push_str(word);
+            }
+        }
+        new_lines.push(new_line);
+        if new_lines[..] == lines[start..end] {
+            return;
+        }
+        let length = Length {
+            line_count: end - 1 - start,
+            byte_count: lines[end - 1].len(),
+        };
+        drop(text);
+        self.document.edit(
             self.id,
             EditKind::Other,
             &self.selection_state.borrow().selections,
-            |mut editor, line_index| {
-                let indent_column_count = editor.as_text().as_lines()[line_index]
-                    .indent()
-                    .unwrap_or("")
-                    .len();
-                let column_count = indent_column_count.min(
-                    (indent_column_count + self.settings.tab_column_count - 1)
-                        % self.settings.tab_column_count
-                        + 1,
-                );
+            |mut editor| {
+                let start = Position {
+                    line_index: start,
+                    byte_index: 0,
+                };
                 editor.apply_edit(Edit {
-                    change: Change::Delete(
-                        Position {
-                            line_index,
-                            byte_index: indent_column_count - column_count,
-                        },
-                        Length {
-                            line_count: 0,
-                            byte_count: column_count,
-                        },
-                    ),
+                    change: Change::Delete(start, length),
+                    drift: Drift::Before,
+                });
+                editor.apply_edit(Edit {
+                    change: Change::Insert(start, new_lines.join("\n").into()),
                     drift: Drift::Before,
                 });
             },
@@ -788,19 +1863,42 @@ impl CodeSession {
     pub fn copy(&self) -> String {
         let mut string = String::new();
         for selection in &self.selection_state.borrow().selections {
-            write!(
-                &mut string,
-                "{}",
-                self.document
+            string.push_str(
+                &self
+                    .document
                     .as_text()
                     .slice(selection.start(), selection.length())
-            )
-            .unwrap();
+                    .to_string_with_line_ending(self.document.line_ending()),
+            );
         }
         string
     }
 
+    pub fn copy_segments(&self) -> Vec<String> {
+        self.merged_selections()
+            .into_iter()
+            .map(|selection| {
+                self.document
+                    .as_text()
+                    .slice(selection.start(), selection.length())
+                    .to_string_with_line_ending(self.document.line_ending())
+            })
+            .collect()
+    }
+
+    /// Returns the text in `range`, with both ends clamped to the document. The result is empty
+    /// if an end does not lie on a char boundary.
+    pub fn text_in_range(&self, range: Range<Position>) -> Text {
+        let start = self.clamp_position(range.start);
+        let end = self.clamp_position(range.end).max(start);
+        self.document
+            .as_text()
+            .try_slice(start, end - start)
+            .unwrap_or_default()
+    }
+
     pub fn undo(&self) -> bool {
+        self.cancel_composition();
         self.selection_state
             .borrow_mut()
             .injected_char_stack
@@ -810,6 +1908,7 @@ impl CodeSession {
     }
 
     pub fn redo(&self) -> bool {
+        self.cancel_composition();
         self.selection_state
             .borrow_mut()
             .injected_char_stack
@@ -818,12 +1917,76 @@ impl CodeSession {
             .redo(self.id, &self.selection_state.borrow().selections)
     }
 
+    pub fn jump_to_undo_group(&self, group_count: usize) -> bool {
+        self.selection_state
+            .borrow_mut()
+            .injected_char_stack
+            .clear();
+        self.document.jump_to_undo_group(
+            self.id,
+            &self.selection_state.borrow().selections,
+            group_count,
+        )
+    }
+
     pub fn handle_changes(&mut self) {
+        self.receive_edits();
+    }
+
+    fn receive_edits(&self) {
         while let Ok((selections, edits)) = self.edit_receiver.try_recv() {
             self.update_after_edit(selections, &edits);
         }
     }
 
+    fn remove_composition(&self) -> Option<(Position, Text)> {
+        let range = self.selection_state.borrow_mut().composition_range.take()?;
+        if range.start != range.end && !self.document.has_provisional_edits(self.id) {
+            // A recorded edit has committed the composed text in the meantime.
+            return None;
+        }
+        let text = self.text_in_range(range.clone());
+        if !text.is_empty() {
+            self.document.apply_provisional_edits(
+                self.id,
+                vec![Edit {
+                    change: Change::Delete(range.start, range.end - range.start),
+                    drift: Drift::Before,
+                }],
+            );
+            self.receive_edits();
+        }
+        Some((range.start, text))
+    }
+
+    fn select_snippet_stop(&self, index: usize) {
+        let mut selection_state = self.selection_state.borrow_mut();
+        let mut selections = SelectionSet::new();
+        for (stop_index, &position) in selection_state.snippet_stops[index].iter().enumerate() {
+            let selection = Selection::from(Cursor {
+                position,
+                affinity: Affinity::Before,
+                preferred_column_index: None,
+            });
+            if stop_index == 0 {
+                selections.set_selection(selection);
+            } else {
+                selections.add_selection(selection);
+            }
+        }
+        selection_state.selections = selections;
+        selection_state.last_added_selection_index = Some(0);
+        selection_state.injected_char_stack.clear();
+        selection_state.snippet_stop_index = index;
+        if index + 1 == selection_state.snippet_stops.len() {
+            // The final tab stop ends the snippet.
+            selection_state.snippet_stops.clear();
+        }
+        drop(selection_state);
+        self.update_highlighted_delimiter_positions();
+        self.update_cursor_position();
+    }
+
     fn modify_selections(
         &self,
         reset_anchor: bool,
@@ -849,16 +2012,77 @@ impl CodeSession {
         drop(selection_state);
         drop(layout);
         self.update_highlighted_delimiter_positions();
+        self.update_cursor_position();
         self.document().force_new_group();
     }
 
+    fn virtual_column_counts(&self) -> Vec<usize> {
+        let layout = self.layout();
+        self.selection_state
+            .borrow()
+            .selections
+            .iter()
+            .map(|selection| {
+                if self.settings.virtual_space && selection.is_empty() {
+                    selection
+                        .cursor
+                        .virtual_column_count(layout.line(selection.cursor.position.line_index))
+                } else {
+                    0
+                }
+            })
+            .collect()
+    }
+
+    fn reindent_lines(&self, f: impl Fn(usize) -> usize) {
+        let tab_column_count = self.settings.tab_column_count;
+        self.document.edit_linewise(
+            self.id,
+            EditKind::Other,
+            &self.selection_state.borrow().selections,
+            |mut editor, line_index| {
+                let (byte_index, delete_byte_count, insert_text) =
+                    reindent(&editor.as_text().as_lines()[line_index], tab_column_count, &f);
+                let position = Position {
+                    line_index,
+                    byte_index,
+                };
+                if delete_byte_count > 0 {
+                    editor.apply_edit(Edit {
+                        change: Change::Delete(
+                            position,
+                            Length {
+                                line_count: 0,
+                                byte_count: delete_byte_count,
+                            },
+                        ),
+                        drift: Drift::Before,
+                    });
+                }
+                if !insert_text.is_empty() {
+                    editor.apply_edit(Edit {
+                        change: Change::Insert(position, insert_text.into()),
+                        drift: Drift::Before,
+                    });
+                }
+            },
+        );
+    }
+
     fn update_after_edit(&self, selections: Option<SelectionSet>, edits: &[Edit]) {
+        let mut dirty_line_indices = Vec::new();
         for edit in edits {
             match edit.change {
                 Change::Insert(point, ref text) => {
                     self.layout.borrow_mut().column_count[point.line_index] = None;
                     self.layout.borrow_mut().wrap_data[point.line_index] = None;
                     let line_count = text.length().line_count;
+                    for line_index in &mut dirty_line_indices {
+                        if *line_index > point.line_index {
+                            *line_index += line_count;
+                        }
+                    }
+                    dirty_line_indices.extend(point.line_index..point.line_index + line_count + 1);
                     if line_count > 0 {
                         let line = point.line_index + 1;
                         self.layout.borrow_mut().y.truncate(line);
@@ -884,6 +2108,15 @@ impl CodeSession {
                     self.layout.borrow_mut().column_count[start.line_index] = None;
                     self.layout.borrow_mut().wrap_data[start.line_index] = None;
                     let line_count = length.line_count;
+                    dirty_line_indices.retain(|&line_index| {
+                        line_index <= start.line_index || line_index > start.line_index + line_count
+                    });
+                    for line_index in &mut dirty_line_indices {
+                        if *line_index > start.line_index {
+                            *line_index -= line_count;
+                        }
+                    }
+                    dirty_line_indices.push(start.line_index);
                     if line_count > 0 {
                         let start_line = start.line_index + 1;
                         let end_line = start_line + line_count;
@@ -905,8 +2138,9 @@ impl CodeSession {
                 }
             }
         }
-        let line_count = self.document.as_text().as_lines().len();
-        for line in 0..line_count {
+        dirty_line_indices.sort_unstable();
+        dirty_line_indices.dedup();
+        for line in dirty_line_indices {
             if self.layout.borrow().wrap_data[line].is_none() {
                 self.update_wrap_data(line);
             }
@@ -923,16 +2157,76 @@ impl CodeSession {
                     .apply_edit(edit, last_added_selection_index);
             }
         }
+        // The selections may have been replaced, or merged by an edit from another session, so
+        // keep the last added index within bounds.
+        let selection_count = selection_state.selections.len();
+        if let Some(index) = &mut selection_state.last_added_selection_index {
+            *index = (*index).min(selection_count.saturating_sub(1));
+        }
+        for stops in &mut selection_state.snippet_stops {
+            for stop in stops {
+                for edit in edits {
+                    *stop = stop.apply_edit(edit);
+                }
+            }
+        }
+        if let Some(range) = &mut selection_state.composition_range {
+            for edit in edits {
+                range.start = range.start.apply_edit(edit);
+                range.end = range.end.apply_edit(edit);
+            }
+        }
+        if self.settings.virtual_space {
+            // An edit takes every cursor out of virtual space.
+            let last_added_selection_index = selection_state.last_added_selection_index;
+            selection_state.last_added_selection_index = selection_state
+                .selections
+                .update_all_selections(last_added_selection_index, |selection| {
+                    selection.update_cursor(|cursor| Cursor {
+                        preferred_column_index: None,
+                        ..cursor
+                    })
+                });
+        }
         drop(selection_state);
+        // Selections that were replaced, for instance by ones from before a reload, may no
+        // longer lie within the text.
+        self.clamp_selections();
         self.update_highlighted_delimiter_positions();
+        self.update_cursor_position();
+    }
+
+    fn clamp_selections(&self) {
+        let is_in_bounds = |position| self.clamp_position(position) == position;
+        let mut selection_state = self.selection_state.borrow_mut();
+        if selection_state.selections.iter().all(|selection| {
+            is_in_bounds(selection.anchor) && is_in_bounds(selection.cursor.position)
+        }) {
+            return;
+        }
+        let last_added_selection_index = selection_state.last_added_selection_index;
+        selection_state.last_added_selection_index = selection_state
+            .selections
+            .update_all_selections(last_added_selection_index, |selection| Selection {
+                cursor: Cursor {
+                    position: self.clamp_position(selection.cursor.position),
+                    ..selection.cursor
+                },
+                anchor: self.clamp_position(selection.anchor),
+            });
     }
 
     fn update_y(&self) {
-        let start = self.layout.borrow().y.len();
+        let mut start = self.layout.borrow().y.len();
         let end = self.document.as_text().as_lines().len();
         if start == end + 1 {
             return;
         }
+        if start > end + 1 {
+            // There are more y coordinates than lines, so none of them can be trusted.
+            self.layout.borrow_mut().y.clear();
+            start = 0;
+        }
         let mut y = if start == 0 {
             0.0
         } else {
@@ -956,16 +2250,36 @@ impl CodeSession {
         }
         ys.push(y);
         self.layout.borrow_mut().y = ys;
+        // The content height may have shrunk (for instance, because lines were folded), so make
+        // sure the scroll offset is still within bounds.
+        let scroll_offset = self.scroll_offset.get();
+        self.scroll_offset
+            .set(dvec2(scroll_offset.x, scroll_offset.y.min(y).max(0.0)));
     }
 
     fn update_column_count(&self, index: usize) {
         let mut column_count = 0;
         let mut column = 0;
+        let mut byte_index = 0;
         let layout = self.layout();
         let line = layout.line(index);
         for wrapped in line.wrapped_elements() {
             match wrapped {
-                WrappedElement::Text { text, .. } => {
+                WrappedElement::Text {
+                    is_inlay: false,
+                    text,
+                } => {
+                    // Measure grapheme by grapheme, the same way the text is drawn.
+                    let range = byte_index..byte_index + text.len();
+                    for grapheme in line.text().graphemes_in_range(range) {
+                        column += grapheme.column_count_at(column, line.tab_column_count());
+                    }
+                    byte_index += text.len();
+                }
+                WrappedElement::Text {
+                    is_inlay: true,
+                    text,
+                } => {
                     column += text.column_count();
                 }
                 WrappedElement::Widget(widget) => {
@@ -986,7 +2300,7 @@ impl CodeSession {
             Some(wrap_column) => {
                 let layout = self.layout();
                 let line = layout.line(line);
-                wrap::compute_wrap_data(line, wrap_column)
+                wrap::compute_wrap_data(line, wrap_column, self.settings.wrap_indent)
             }
             None => WrapData::default(),
         };
@@ -1000,6 +2314,7 @@ impl CodeSession {
         let mut highlighted_delimiter_positions =
             mem::take(&mut selection_state.highlighted_delimiter_positions);
         highlighted_delimiter_positions.clear();
+        let document_layout = self.document.layout();
         for selection in &selection_state.selections {
             if !selection.is_empty() {
                 continue;
@@ -1007,6 +2322,7 @@ impl CodeSession {
             if let Some((opening_delimiter_position, closing_delimiter_position)) =
                 find_highlighted_delimiter_pair(
                     self.document.as_text().as_lines(),
+                    &document_layout.tokens,
                     selection.cursor.position,
                 )
             {
@@ -1016,6 +2332,14 @@ impl CodeSession {
         }
         selection_state.highlighted_delimiter_positions = highlighted_delimiter_positions;
     }
+
+    fn update_cursor_position(&self) {
+        let selection_state = self.selection_state.borrow();
+        let selection = selection_state.selections
+            [selection_state.last_added_selection_index.unwrap_or(0)];
+        self.document
+            .set_cursor_position(self.id, selection.cursor.position);
+    }
 }
 
 impl Drop for CodeSession {
@@ -1024,9 +2348,30 @@ impl Drop for CodeSession {
     }
 }
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub struct SessionId(usize);
 
+#[derive(Clone, Debug, PartialEq)]
+pub struct LaidOutLine {
+    pub line_index: usize,
+    pub y: f64,
+    pub height: f64,
+    pub scale: f64,
+    pub fold_column_index: usize,
+    pub wrap_data: Option<WrapData>,
+    pub is_folded: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LineGutterInfo {
+    pub y: f64,
+    pub height: f64,
+    pub scale: f64,
+    pub is_folded: bool,
+    /// Whether the lines after this one are folded, or folding, into it.
+    pub fold_header: bool,
+}
+
 #[derive(Debug)]
 pub struct SessionLayout {
     pub y: Vec<f64>,
@@ -1034,6 +2379,7 @@ pub struct SessionLayout {
     pub fold_column: Vec<usize>,
     pub scale: Vec<f64>,
     pub wrap_data: Vec<Option<WrapData>>,
+    pub tab_column_count: usize,
 }
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
@@ -1051,6 +2397,9 @@ struct SelectionState {
     last_added_selection_index: Option<usize>,
     injected_char_stack: Vec<char>,
     highlighted_delimiter_positions: HashSet<Position>,
+    snippet_stops: Vec<Vec<Position>>,
+    snippet_stop_index: usize,
+    composition_range: Option<Range<Position>>,
 }
 
 #[derive(Debug)]
@@ -1058,13 +2407,25 @@ struct FoldState {
     folding_lines: HashSet<usize>,
     folded_lines: HashSet<usize>,
     unfolding_lines: HashSet<usize>,
+    animation_finished: bool,
 }
 
-pub fn reindent(string: &str, f: impl FnOnce(usize) -> usize) -> (usize, usize, String) {
+pub fn reindent(
+    string: &str,
+    tab_column_count: usize,
+    f: impl FnOnce(usize) -> usize,
+) -> (usize, usize, String) {
     let indentation = string.indent().unwrap_or("");
-    let indentation_column_count = indentation.column_count();
+    let indentation_column_count = indentation.column_count_at(0, tab_column_count);
     let new_indentation_column_count = f(indentation_column_count);
-    let new_indentation = new_indentation(new_indentation_column_count);
+    // Keep as many of the leading tabs as still fit, and make up the rest with spaces.
+    let tab_count = indentation
+        .chars()
+        .take_while(|&char| char == '\t')
+        .count()
+        .min(new_indentation_column_count / tab_column_count);
+    let new_indentation = "\t".repeat(tab_count)
+        + &new_indentation(new_indentation_column_count - tab_count * tab_column_count);
     let len = indentation.longest_common_prefix(&new_indentation).len();
     (
         len,
@@ -1077,16 +2438,16 @@ fn grow_selection(
     selection: Selection,
     lines: &[String],
     mode: SelectionMode,
-    word_separators: &[char],
+    extra_word_chars: &[char],
 ) -> Selection {
     match mode {
         SelectionMode::Simple => selection,
         SelectionMode::Word => {
             let position = selection.cursor.position;
             let start_byte_index = lines[position.line_index]
-                .find_prev_word_boundary(position.byte_index, word_separators);
+                .find_prev_word_boundary(position.byte_index, extra_word_chars);
             let end_byte_index = lines[position.line_index]
-                .find_next_word_boundary(position.byte_index, word_separators);
+                .find_next_word_boundary(position.byte_index, extra_word_chars);
             if selection.anchor < selection.cursor.position {
                 Selection {
                     cursor: Cursor {
@@ -1194,145 +2555,101 @@ fn grow_selection(
     }
 }
 
-fn new_indentation(column_count: usize) -> String {
-    iter::repeat(' ').take(column_count).collect()
-}
-
-fn find_highlighted_delimiter_pair(
-    lines: &[String],
-    position: Position,
-) -> Option<(Position, Position)> {
-    // Cursor is before an opening delimiter
-    match lines[position.line_index][position.byte_index..]
-        .chars()
-        .next()
-    {
-        Some(ch) if ch.is_opening_delimiter() => {
-            let opening_delimiter_position = position;
-            if let Some(closing_delimiter_position) = find_closing_delimiter(
-                lines,
-                Position {
-                    line_index: position.line_index,
-                    byte_index: position.byte_index + ch.len_utf8(),
-                },
-                ch,
-            ) {
-                return Some((opening_delimiter_position, closing_delimiter_position));
-            }
-        }
-        _ => {}
-    }
-    // Cursor is before a closing delimiter
-    match lines[position.line_index][position.byte_index..]
-        .chars()
-        .next()
-    {
-        Some(ch) if ch.is_closing_delimiter() => {
-            let closing_delimiter_position = position;
-            if let Some(opening_delimiter_position) = find_opening_delimiter(lines, position, ch) {
-                return Some((opening_delimiter_position, closing_delimiter_position));
-            }
-        }
-        _ => {}
+fn line_comment_prefix<'a>(line: &'a str, line_comment: &str) -> Option<&'a str> {
+    let indent_len = line.indent().unwrap_or("").len();
+    let comment = &line[indent_len..];
+    if !comment.starts_with(line_comment) {
+        return None;
     }
-    // Cursor is after a closing delimiter
-    match lines[position.line_index][..position.byte_index]
+    // Include any doc comment markers that directly follow the comment token (as in `///` or
+    // `//!`), followed by the whitespace that separates the prefix from the comment text. Other
+    // punctuation, as in `//-----`, is comment text.
+    let marker_len = comment[line_comment.len()..]
         .chars()
-        .next_back()
-    {
-        Some(ch) if ch.is_closing_delimiter() => {
-            let closing_delimiter_position = Position {
-                line_index: position.line_index,
-                byte_index: position.byte_index - ch.len_utf8(),
-            };
-            if let Some(opening_delimiter_position) =
-                find_opening_delimiter(lines, closing_delimiter_position, ch)
-            {
-                return Some((opening_delimiter_position, closing_delimiter_position));
-            }
-        }
-        _ => {}
-    }
-    // Cursor is after an opening delimiter
-    match lines[position.line_index][..position.byte_index]
+        .take_while(|&char| char == '/' || char == '!')
+        .map(|char| char.len_utf8())
+        .sum::<usize>();
+    let whitespace_len = comment[line_comment.len() + marker_len..]
         .chars()
-        .next_back()
-    {
-        Some(ch) if ch.is_opening_delimiter() => {
-            let opening_delimiter_position = Position {
-                line_index: position.line_index,
-                byte_index: position.byte_index - ch.len_utf8(),
-            };
-            if let Some(closing_delimiter_position) = find_closing_delimiter(lines, position, ch) {
-                return Some((opening_delimiter_position, closing_delimiter_position));
-            }
-        }
-        _ => {}
-    }
-    None
+        .take_while(|char| char.is_whitespace())
+        .map(|char| char.len_utf8())
+        .sum::<usize>();
+    Some(&comment[..line_comment.len() + marker_len + whitespace_len])
 }
 
-fn find_opening_delimiter(
-    lines: &[String],
-    position: Position,
-    closing_delimiter: char,
-) -> Option<Position> {
-    let mut delimiter_stack = vec![closing_delimiter];
-    let mut position = position;
-    loop {
-        for char in lines[position.line_index][..position.byte_index]
-            .chars()
-            .rev()
-        {
-            position.byte_index -= char.len_utf8();
-            if char.is_closing_delimiter() {
-                delimiter_stack.push(char);
-            }
-            if char.is_opening_delimiter() {
-                if delimiter_stack.last() != Some(&char.opposite_delimiter().unwrap()) {
-                    return None;
-                }
-                delimiter_stack.pop().unwrap();
-                if delimiter_stack.is_empty() {
-                    return Some(position);
-                }
-            }
-        }
-        if position.line_index == 0 {
-            return None;
-        }
-        position.line_index -= 1;
-        position.byte_index = lines[position.line_index].len();
+fn paragraph_prefix<'a>(line: &'a str, line_comment: Option<&str>) -> (&'a str, String) {
+    let indent = line.indent().unwrap_or(line);
+    match line_comment.and_then(|line_comment| line_comment_prefix(line, line_comment)) {
+        Some(comment_prefix) => (
+            &line[..indent.len() + comment_prefix.len()],
+            format!("{}{}", indent, comment_prefix.trim_end()),
+        ),
+        None => (indent, indent.to_string()),
     }
 }
 
-fn find_closing_delimiter(
-    lines: &[String],
-    position: Position,
-    opening_delimiter: char,
-) -> Option<Position> {
-    let mut delimiter_stack = vec![opening_delimiter];
-    let mut position = position;
-    loop {
-        for char in lines[position.line_index][position.byte_index..].chars() {
-            if char.is_opening_delimiter() {
-                delimiter_stack.push(char);
+fn new_indentation(column_count: usize) -> String {
+    " ".repeat(column_count)
+}
+
+/// Splits a snippet into the text to insert and its tab stops, as lengths from the start of the
+/// text grouped by tab stop number in the order they are visited. Lines after the first get
+/// `indent` prepended.
+fn parse_snippet(snippet: &str, indent: &str) -> (Text, Vec<Vec<Length>>) {
+    let mut string = String::new();
+    let mut line_count = 0;
+    let mut line_start = 0;
+    let mut stops = Vec::new();
+    let mut chars = snippet.chars().peekable();
+    while let Some(char) = chars.next() {
+        match char {
+            '\\' if chars.peek() == Some(&'$') => {
+                chars.next();
+                string.push('$');
             }
-            if char.is_closing_delimiter() {
-                if delimiter_stack.last() != Some(&char.opposite_delimiter().unwrap()) {
-                    return None;
-                }
-                delimiter_stack.pop().unwrap();
-                if delimiter_stack.is_empty() {
-                    return Some(position);
+            '$' if chars.peek().is_some_and(|char| char.is_ascii_digit()) => {
+                let mut number = 0;
+                while let Some(digit) = chars.peek().and_then(|char| char.to_digit(10)) {
+                    chars.next();
+                    number = number * 10 + digit as usize;
                 }
+                stops.push((
+                    number,
+                    Length {
+                        line_count,
+                        byte_count: string.len() - line_start,
+                    },
+                ));
+            }
+            '\n' => {
+                string.push('\n');
+                line_count += 1;
+                line_start = string.len();
+                string.push_str(indent);
             }
-            position.byte_index += char.len_utf8();
+            char => string.push(char),
         }
-        if position.line_index == lines.len() - 1 {
-            return None;
+    }
+    if stops.iter().all(|&(number, _)| number != 0) {
+        stops.push((
+            0,
+            Length {
+                line_count,
+                byte_count: string.len() - line_start,
+            },
+        ));
+    }
+    // `$0` is the final tab stop, after all the numbered ones.
+    stops.sort_by_key(|&(number, _)| (number == 0, number));
+    let mut groups: Vec<(usize, Vec<Length>)> = Vec::new();
+    for (number, length) in stops {
+        match groups.last_mut() {
+            Some((last_number, lengths)) if *last_number == number => lengths.push(length),
+            _ => groups.push((number, vec![length])),
         }
-        position.line_index += 1;
-        position.byte_index = 0;
     }
+    (
+        string.into(),
+        groups.into_iter().map(|(_, lengths)| lengths).collect(),
+    )
 }
@@ -1,19 +1,45 @@
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Settings {
     pub tab_column_count: usize,
+    /// The number of columns that one indent level takes up, or `None` to use
+    /// `tab_column_count`.
+    pub indent_column_count: Option<usize>,
     pub fold_level: usize,
-    pub word_separators: Vec<char>,
+    pub fold_ellipsis: String,
+    pub extra_word_chars: Vec<char>,
+    pub auto_close_delimiters: bool,
+    /// The token that starts a line comment, such as `//`, so that comments are continued on
+    /// enter and reflowed as paragraphs, or `None` to treat comments as plain text.
+    pub line_comment: Option<String>,
+    pub wrap_indent: WrapIndent,
+    pub virtual_space: bool,
+}
+
+impl Settings {
+    pub fn indent_column_count(&self) -> usize {
+        self.indent_column_count.unwrap_or(self.tab_column_count)
+    }
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Self {
             tab_column_count: 4,
+            indent_column_count: None,
             fold_level: 2,
-            word_separators: vec![
-                ' ', '`', '~', '!', '@', '#', '$', '%', '^', '&', '*', '(', ')', '-', '=', '+',
-                '[', '{', ']', '}', '\\', '|', ';', ':', '\'', '"', '.', '<', '>', '/', '?', ',',
-            ],
+            fold_ellipsis: "…".to_string(),
+            extra_word_chars: Vec::new(),
+            auto_close_delimiters: true,
+            line_comment: None,
+            wrap_indent: WrapIndent::MatchIndent,
+            virtual_space: false,
         }
     }
 }
+
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum WrapIndent {
+    None,
+    MatchIndent,
+    Hanging(usize),
+}
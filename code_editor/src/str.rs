@@ -1,16 +1,18 @@
-use crate::char::CharExt;
+use {crate::char::CharExt, std::ops::Range};
 
 pub trait StrExt {
     fn column_count(&self) -> usize;
+    fn column_count_at(&self, column_index: usize, tab_column_count: usize) -> usize;
     fn indent_level(&self, indent_column_count: usize) -> usize;
     fn next_indent_level(&self, indent_column_count: usize) -> usize;
     fn prev_indent_level(&self, indent_column_count: usize) -> usize;
-    fn find_next_word_boundary(&self, index: usize, word_separators: &[char]) -> usize;
-    fn find_prev_word_boundary(&self, index: usize, word_separators: &[char]) -> usize;
+    fn find_next_word_boundary(&self, index: usize, extra_word_chars: &[char]) -> usize;
+    fn find_prev_word_boundary(&self, index: usize, extra_word_chars: &[char]) -> usize;
     fn indent(&self) -> Option<&str>;
     fn longest_common_prefix(&self, other: &str) -> &str;
     fn graphemes(&self) -> Graphemes<'_>;
     fn grapheme_indices(&self) -> GraphemeIndices<'_>;
+    fn graphemes_in_range(&self, range: Range<usize>) -> GraphemesInRange<'_>;
     fn split_whitespace_boundaries(&self) -> SplitWhitespaceBoundaries<'_>;
 }
 
@@ -19,6 +21,18 @@ impl StrExt for str {
         self.chars().map(|char| char.column_count()).sum()
     }
 
+    fn column_count_at(&self, column_index: usize, tab_column_count: usize) -> usize {
+        let mut current_column_index = column_index;
+        for char in self.chars() {
+            if char == '\t' {
+                current_column_index += tab_column_count - current_column_index % tab_column_count;
+            } else {
+                current_column_index += char.column_count();
+            }
+        }
+        current_column_index - column_index
+    }
+
     fn indent_level(&self, indent_column_count: usize) -> usize {
         self.indent().unwrap_or("").column_count() / indent_column_count
     }
@@ -31,25 +45,25 @@ impl StrExt for str {
         self.indent().unwrap_or("").column_count().saturating_sub(1) / indent_column_count
     }
 
-    fn find_next_word_boundary(&self, index: usize, word_separators: &[char]) -> usize {
+    fn find_next_word_boundary(&self, index: usize, extra_word_chars: &[char]) -> usize {
         if index == 0 {
             return index;
         }
         let start = index;
         self[index..]
             .char_indices()
-            .find(|&(_, char)| word_separators.contains(&char))
+            .find(|&(_, char)| !char.is_word_char(extra_word_chars))
             .map(|(index, _)| start + index)
             .unwrap_or_else(|| self.len())
     }
 
-    fn find_prev_word_boundary(&self, index: usize, word_separators: &[char]) -> usize {
+    fn find_prev_word_boundary(&self, index: usize, extra_word_chars: &[char]) -> usize {
         if index == self.len() {
             return index;
         }
         self[..index]
             .char_indices()
-            .rfind(|&(_, char)| word_separators.contains(&char))
+            .rfind(|&(_, char)| !char.is_word_char(extra_word_chars))
             .map(|(char_index, char)| char_index + char.len_utf8())
             .unwrap_or(0)
     }
@@ -64,13 +78,15 @@ impl StrExt for str {
         &self[..self
             .char_indices()
             .zip(other.chars())
-            .find(|((_, char_0), char_1)| char_0 == char_1)
+            .find(|((_, char_0), char_1)| char_0 != char_1)
             .map(|((index, _), _)| index)
             .unwrap_or_else(|| self.len().min(other.len()))]
     }
 
     fn graphemes(&self) -> Graphemes<'_> {
-        Graphemes { string: self }
+        Graphemes {
+            graphemes: unicode_segmentation::UnicodeSegmentation::graphemes(self, true),
+        }
     }
 
     fn grapheme_indices(&self) -> GraphemeIndices<'_> {
@@ -80,45 +96,48 @@ impl StrExt for str {
         }
     }
 
+    /// Returns the graphemes of the string that start in the given byte range.
+    ///
+    /// Grapheme boundaries are those of the whole string, not of the range, so a grapheme that
+    /// straddles the end of the range (e.g. a base character on one side of a wrap and its
+    /// combining mark on the other) is returned whole, and skipped by the range that follows.
+    fn graphemes_in_range(&self, range: Range<usize>) -> GraphemesInRange<'_> {
+        let mut cursor = unicode_segmentation::GraphemeCursor::new(range.start, self.len(), true);
+        let start = if cursor.is_boundary(self, 0).unwrap() {
+            range.start
+        } else {
+            cursor.prev_boundary(self, 0).unwrap().unwrap_or(0)
+        };
+        GraphemesInRange {
+            grapheme_indices: self[start..].grapheme_indices(),
+            offset: start,
+            range,
+        }
+    }
+
     fn split_whitespace_boundaries(&self) -> SplitWhitespaceBoundaries<'_> {
         SplitWhitespaceBoundaries { string: self }
     }
 }
 
+/// An iterator over the extended grapheme clusters of a string, so that combining marks, emoji
+/// modifiers, ZWJ sequences and flags each come out as a single grapheme.
 #[derive(Clone, Debug)]
 pub struct Graphemes<'a> {
-    string: &'a str,
+    graphemes: unicode_segmentation::Graphemes<'a>,
 }
 
 impl<'a> Iterator for Graphemes<'a> {
     type Item = &'a str;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.string.is_empty() {
-            return None;
-        }
-        let mut end = 1;
-        while !self.string.is_char_boundary(end) {
-            end += 1;
-        }
-        let (grapheme, string) = self.string.split_at(end);
-        self.string = string;
-        Some(grapheme)
+        self.graphemes.next()
     }
 }
 
 impl<'a> DoubleEndedIterator for Graphemes<'a> {
     fn next_back(&mut self) -> Option<Self::Item> {
-        if self.string.is_empty() {
-            return None;
-        }
-        let mut start = self.string.len() - 1;
-        while !self.string.is_char_boundary(start) {
-            start -= 1;
-        }
-        let (string, grapheme) = self.string.split_at(start);
-        self.string = string;
-        Some(grapheme)
+        self.graphemes.next_back()
     }
 }
 
@@ -144,6 +163,30 @@ impl<'a> DoubleEndedIterator for GraphemeIndices<'a> {
     }
 }
 
+#[derive(Clone, Debug)]
+pub struct GraphemesInRange<'a> {
+    grapheme_indices: GraphemeIndices<'a>,
+    offset: usize,
+    range: Range<usize>,
+}
+
+impl<'a> Iterator for GraphemesInRange<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (index, grapheme) = self.grapheme_indices.next()?;
+            let index = self.offset + index;
+            if index >= self.range.end {
+                return None;
+            }
+            if index >= self.range.start {
+                return Some(grapheme);
+            }
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct SplitWhitespaceBoundaries<'a> {
     string: &'a str,
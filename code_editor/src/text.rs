@@ -3,7 +3,8 @@ use std::{
     fmt, io,
     io::BufRead,
     iter,
-    ops::{Add, AddAssign, Sub, SubAssign},
+    ops::{Add, AddAssign, Deref, Sub, SubAssign},
+    sync::Arc,
 };
 
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
@@ -22,13 +23,28 @@ impl Text {
         }
     }
 
-    pub fn from_buf_reader<R>(reader: R) -> io::Result<Self>
+    /// Reads the text one line at a time, so the input is never held in a single `String`. Lines
+    /// are split on `'\n'` only, exactly like `Text::from(&str)`: a trailing `'\r'` is kept so
+    /// that line endings can be detected, and a final `'\n'` yields an empty last line.
+    pub fn from_buf_reader<R>(mut reader: R) -> io::Result<Self>
     where
         R: BufRead,
     {
-        Ok(Self {
-            lines: reader.lines().collect::<Result<_, _>>()?,
-        })
+        let mut lines = Vec::new();
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line)?;
+            let is_last = !line.ends_with('\n');
+            if !is_last {
+                line.pop();
+            }
+            line.shrink_to_fit();
+            lines.push(line);
+            if is_last {
+                break;
+            }
+        }
+        Ok(Self { lines })
     }
 
     pub fn is_empty(&self) -> bool {
@@ -57,22 +73,123 @@ impl Text {
         &self.lines
     }
 
+    pub fn line_ending(&self) -> LineEnding {
+        let (_, remaining_lines) = self.lines.split_last().unwrap();
+        let crlf_count = remaining_lines
+            .iter()
+            .filter(|line| line.ends_with('\r'))
+            .count();
+        if crlf_count > remaining_lines.len() - crlf_count {
+            LineEnding::CrLf
+        } else {
+            LineEnding::Lf
+        }
+    }
+
+    pub fn normalize_line_endings(&mut self) {
+        let line_count = self.lines.len();
+        for line in &mut self.lines[..line_count - 1] {
+            if line.ends_with('\r') {
+                line.pop();
+            }
+        }
+    }
+
+    pub fn to_string_with_line_ending(&self, line_ending: LineEnding) -> String {
+        self.lines.join(line_ending.as_str())
+    }
+
+    pub fn position_to_offset(&self, position: Position) -> usize {
+        if position.line_index >= self.lines.len() {
+            return self.lines.iter().map(|line| line.len() + 1).sum::<usize>() - 1;
+        }
+        self.lines[..position.line_index]
+            .iter()
+            .map(|line| line.len() + 1)
+            .sum::<usize>()
+            + position.byte_index.min(self.lines[position.line_index].len())
+    }
+
+    pub fn offset_to_position(&self, offset: usize) -> Position {
+        let mut offset = offset;
+        for (line_index, line) in self.lines.iter().enumerate() {
+            if offset <= line.len() {
+                return Position {
+                    line_index,
+                    byte_index: offset,
+                };
+            }
+            offset -= line.len() + 1;
+        }
+        Position {
+            line_index: self.lines.len() - 1,
+            byte_index: self.lines.last().unwrap().len(),
+        }
+    }
+
+    pub fn position_to_lsp(&self, position: Position) -> (u32, u32) {
+        if position.line_index >= self.lines.len() {
+            let line_index = self.lines.len() - 1;
+            return self.position_to_lsp(Position {
+                line_index,
+                byte_index: self.lines[line_index].len(),
+            });
+        }
+        let line = &self.lines[position.line_index];
+        let byte_index = position.byte_index.min(line.len());
+        let utf16_column_index = line
+            .char_indices()
+            .take_while(|&(index, _)| index < byte_index)
+            .map(|(_, char)| char.len_utf16())
+            .sum::<usize>();
+        (position.line_index as u32, utf16_column_index as u32)
+    }
+
+    pub fn lsp_to_position(&self, line_index: u32, utf16_column_index: u32) -> Position {
+        let line_index = (line_index as usize).min(self.lines.len() - 1);
+        let line = &self.lines[line_index];
+        let mut current_utf16_column_index = 0;
+        let byte_index = line
+            .char_indices()
+            .find_map(|(index, char)| {
+                current_utf16_column_index += char.len_utf16();
+                if current_utf16_column_index > utf16_column_index as usize {
+                    Some(index)
+                } else {
+                    None
+                }
+            })
+            .unwrap_or(line.len());
+        Position {
+            line_index,
+            byte_index,
+        }
+    }
+
     pub fn slice(&self, start: Position, length: Length) -> Self {
+        self.try_slice(start, length).unwrap()
+    }
+
+    /// Like `slice`, but returns `None` instead of panicking if the range lies outside the text
+    /// or does not start and end on a char boundary, as can happen with a stale range.
+    pub fn try_slice(&self, start: Position, length: Length) -> Option<Self> {
         let end = start + length;
+        let start_line = self.lines.get(start.line_index)?;
+        let end_line = self.lines.get(end.line_index)?;
         let mut lines = Vec::new();
         if start.line_index == end.line_index {
-            lines.push(self.lines[start.line_index][start.byte_index..end.byte_index].to_string());
+            lines.push(start_line.get(start.byte_index..end.byte_index)?.to_string());
         } else {
             lines.reserve(end.line_index - start.line_index + 1);
-            lines.push(self.lines[start.line_index][start.byte_index..].to_string());
+            lines.push(start_line.get(start.byte_index..)?.to_string());
             lines.extend(
                 self.lines[start.line_index + 1..end.line_index]
                     .iter()
                     .cloned(),
             );
-            lines.push(self.lines[end.line_index][..end.byte_index].to_string());
+            lines.push(end_line.get(..end.byte_index)?.to_string());
         }
-        Text { lines }
+        Some(Text { lines })
     }
 
     pub fn apply_change(&mut self, change: Change) {
@@ -119,6 +236,27 @@ impl Text {
     }
 }
 
+/// An immutable copy of a text that is cheap to take and can be sent to another thread. The text
+/// is shared with the document it was taken from until the document is next edited.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct TextSnapshot {
+    text: Arc<Text>,
+}
+
+impl TextSnapshot {
+    pub(crate) fn new(text: Arc<Text>) -> Self {
+        Self { text }
+    }
+}
+
+impl Deref for TextSnapshot {
+    type Target = Text;
+
+    fn deref(&self) -> &Self::Target {
+        &self.text
+    }
+}
+
 impl Default for Text {
     fn default() -> Self {
         Self {
@@ -174,6 +312,21 @@ impl FromIterator<char> for Text {
     }
 }
 
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum LineEnding {
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+        }
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
 pub struct Edit {
     pub change: Change,
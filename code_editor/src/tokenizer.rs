@@ -1,21 +1,103 @@
-use crate::{
-    text::{Change, Text},
-    token::TokenKind,
-    Token,
+use {
+    crate::{
+        text::{Change, Text},
+        token::TokenKind,
+        Token,
+    },
+    std::fmt,
 };
 
+pub trait Tokenizer {
+    type State: Copy + Default + Eq;
+
+    /// Returns the token at the start of `line`, which is never empty, together with the state
+    /// to tokenize the rest of the line with.
+    fn next_token(&mut self, line: &str, state: Self::State) -> (Token, Self::State);
+}
+
+/// The identifiers that `RustTokenizer` highlights as `TokenKind::OtherKeyword` by default.
+pub const KEYWORDS: &[&str] = &[
+    "Self", "as", "async", "await", "const", "crate", "dyn", "enum", "extern", "false", "fn",
+    "impl", "in", "let", "mod", "move", "mut", "pub", "ref", "self", "static", "struct", "super",
+    "trait", "true", "type", "unsafe", "use", "where", "usize", "isize", "u8", "u16", "u32", "u64",
+    "i8", "i16", "i32", "i64", "vec2", "vec3", "vec4", "bool", "f32", "f64",
+];
+
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+pub struct RustTokenizer {
+    keywords: Option<Vec<String>>,
+}
+
+impl RustTokenizer {
+    /// Returns a tokenizer that highlights `keywords` as `TokenKind::OtherKeyword` instead of
+    /// `KEYWORDS`. Branch and loop keywords keep their own kinds either way.
+    pub fn with_keywords(keywords: &[&str]) -> Self {
+        Self {
+            keywords: Some(keywords.iter().map(|keyword| keyword.to_string()).collect()),
+        }
+    }
+}
+
+impl Tokenizer for RustTokenizer {
+    type State = State;
+
+    fn next_token(&mut self, line: &str, state: State) -> (Token, State) {
+        let (mut token, next_state) = match state.next(&mut Cursor::new(line)) {
+            (next_state, Some(token)) => (token, next_state),
+            (next_state, None) => (
+                Token {
+                    len: line.len(),
+                    kind: TokenKind::Unknown,
+                },
+                next_state,
+            ),
+        };
+        if let Some(keywords) = &self.keywords {
+            let string = &line[..token.len];
+            match token.kind {
+                TokenKind::OtherKeyword
+                | TokenKind::Constant
+                | TokenKind::Typename
+                | TokenKind::Function
+                | TokenKind::Identifier => {
+                    token.kind = if keywords.iter().any(|keyword| keyword == string) {
+                        TokenKind::OtherKeyword
+                    } else {
+                        identifier_kind(string, line[token.len..].chars().next().unwrap_or('\0'))
+                    };
+                }
+                _ => {}
+            }
+        }
+        (token, next_state)
+    }
+}
+
 #[derive(Clone, Debug, Eq, Hash, PartialEq)]
-pub struct Tokenizer {
-    state: Vec<Option<(State, State)>>,
+pub struct TokenCache<T: Tokenizer = RustTokenizer> {
+    tokenizer: T,
+    state: Vec<Option<(T::State, T::State)>>,
 }
 
-impl Tokenizer {
+impl TokenCache {
     pub fn new(line_count: usize) -> Self {
+        Self::with_tokenizer(RustTokenizer::default(), line_count)
+    }
+}
+
+impl<T: Tokenizer> TokenCache<T> {
+    pub fn with_tokenizer(tokenizer: T, line_count: usize) -> Self {
         Self {
+            tokenizer,
             state: (0..line_count).map(|_| None).collect(),
         }
     }
 
+    /// Forgets the state of every line, for when the whole text has been replaced.
+    pub fn reset(&mut self, line_count: usize) {
+        self.state = (0..line_count).map(|_| None).collect();
+    }
+
     pub fn apply_change(&mut self, change: &Change) {
         match *change {
             Change::Insert(point, ref text) => {
@@ -39,7 +121,7 @@ impl Tokenizer {
     }
 
     pub fn update(&mut self, text: &Text, tokens: &mut [Vec<Token>]) {
-        let mut state = State::default();
+        let mut state = T::State::default();
         for line in 0..text.as_lines().len() {
             match self.state[line] {
                 Some((start_state, end_state)) if state == start_state => {
@@ -48,14 +130,22 @@ impl Tokenizer {
                 _ => {
                     let start_state = state;
                     let mut new_tokens = Vec::new();
-                    let mut cursor = Cursor::new(&text.as_lines()[line]);
-                    loop {
-                        let (next_state, token) = state.next(&mut cursor);
-                        state = next_state;
-                        match token {
-                            Some(token) => new_tokens.push(token),
-                            None => break,
+                    let line_text = &text.as_lines()[line];
+                    let mut byte_index = 0;
+                    while byte_index < line_text.len() {
+                        let (token, next_state) =
+                            self.tokenizer.next_token(&line_text[byte_index..], state);
+                        if token.len == 0 {
+                            // An empty token would never get us to the end of the line.
+                            new_tokens.push(Token {
+                                len: line_text.len() - byte_index,
+                                kind: TokenKind::Unknown,
+                            });
+                            break;
                         }
+                        state = next_state;
+                        byte_index += token.len;
+                        new_tokens.push(token);
                     }
                     self.state[line] = Some((start_state, state));
                     tokens[line] = new_tokens;
@@ -65,6 +155,33 @@ impl Tokenizer {
     }
 }
 
+/// A `TokenCache` with the type of its tokenizer erased, so that a document can hold any of them.
+pub(crate) trait DynTokenCache: fmt::Debug {
+    fn reset(&mut self, line_count: usize);
+
+    fn apply_change(&mut self, change: &Change);
+
+    fn update(&mut self, text: &Text, tokens: &mut [Vec<Token>]);
+}
+
+impl<T> DynTokenCache for TokenCache<T>
+where
+    T: Tokenizer + fmt::Debug,
+    T::State: fmt::Debug,
+{
+    fn reset(&mut self, line_count: usize) {
+        TokenCache::reset(self, line_count)
+    }
+
+    fn apply_change(&mut self, change: &Change) {
+        TokenCache::apply_change(self, change)
+    }
+
+    fn update(&mut self, text: &Text, tokens: &mut [Vec<Token>]) {
+        TokenCache::update(self, text, tokens)
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub enum State {
     Initial(InitialState),
@@ -224,25 +341,8 @@ impl InitialState {
             match string {
                 "else" | "if" | "match" | "return" => TokenKind::BranchKeyword,
                 "break" | "continue" | "for" | "loop" | "while" => TokenKind::LoopKeyword,
-                "Self" | "as" | "async" | "await" | "const" | "crate" | "dyn" | "enum"
-                | "extern" | "false" | "fn" | "impl" | "in" | "let" | "mod" | "move" | "mut"
-                | "pub" | "ref" | "self" | "static" | "struct" | "super" | "trait" | "true"
-                | "type" | "unsafe" | "use" | "where" | "usize" | "isize" | "u8" | "u16"
-                | "u32" | "u64" | "i8" | "i16" | "i32" | "i64" | "vec2" | "vec3" | "vec4"
-                | "bool" | "f32" | "f64" => TokenKind::OtherKeyword,
-                _ => {
-                    let mut chars = string.chars();
-                    if chars.next().unwrap().is_uppercase() {
-                        match chars.next() {
-                            Some(char) if char.is_uppercase() => TokenKind::Constant,
-                            _ => TokenKind::Typename,
-                        }
-                    } else if cursor.peek(0) == '(' {
-                        TokenKind::Function
-                    } else {
-                        TokenKind::Identifier
-                    }
-                }
+                _ if KEYWORDS.contains(&string) => TokenKind::OtherKeyword,
+                _ => identifier_kind(string, cursor.peek(0)),
             },
         )
     }
@@ -470,6 +570,20 @@ impl RawDoubleQuotedStringTailState {
     }
 }
 
+fn identifier_kind(string: &str, next_char: char) -> TokenKind {
+    let mut chars = string.chars();
+    if chars.next().unwrap().is_uppercase() {
+        match chars.next() {
+            Some(char) if char.is_uppercase() => TokenKind::Constant,
+            _ => TokenKind::Typename,
+        }
+    } else if next_char == '(' {
+        TokenKind::Function
+    } else {
+        TokenKind::Identifier
+    }
+}
+
 #[derive(Debug)]
 pub struct Cursor<'a> {
     string: &'a str,
@@ -1,4 +1,4 @@
-use crate::{char::CharExt, layout::InlineElement, str::StrExt, Line};
+use crate::{layout::InlineElement, settings::WrapIndent, str::StrExt, Line};
 
 #[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
 pub struct WrapData {
@@ -6,14 +6,17 @@ pub struct WrapData {
     pub indent_column_count: usize,
 }
 
-pub fn compute_wrap_data(line: Line<'_>, wrap_column: usize) -> WrapData {
-    let indent_column_count: usize = line
+pub fn compute_wrap_data(line: Line<'_>, wrap_column: usize, wrap_indent: WrapIndent) -> WrapData {
+    let line_indent_column_count = line
         .text
         .indent()
         .unwrap_or("")
-        .chars()
-        .map(|char| char.column_count())
-        .sum();
+        .column_count_at(0, line.tab_column_count);
+    let indent_column_count = match wrap_indent {
+        WrapIndent::None => 0,
+        WrapIndent::MatchIndent => line_indent_column_count,
+        WrapIndent::Hanging(column_count) => line_indent_column_count + column_count,
+    };
     let mut byte_index = 0;
     let mut column_index = 0;
     let mut wraps = Vec::new();
@@ -21,9 +24,11 @@ pub fn compute_wrap_data(line: Line<'_>, wrap_column: usize) -> WrapData {
         match element {
             InlineElement::Text { text, .. } => {
                 for string in text.split_whitespace_boundaries() {
-                    let column_count: usize = string.chars().map(|char| char.column_count()).sum();
+                    let mut column_count =
+                        string.column_count_at(column_index, line.tab_column_count);
                     if column_index + column_count > wrap_column {
                         column_index = indent_column_count;
+                        column_count = string.column_count_at(column_index, line.tab_column_count);
                         wraps.push(byte_index);
                     }
                     column_index += column_count;
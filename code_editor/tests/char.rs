@@ -0,0 +1,23 @@
+use makepad_code_editor::{char::CharExt, str::StrExt};
+
+#[test]
+fn test_column_count_uses_display_width() {
+    assert_eq!('a'.column_count(), 1);
+    assert_eq!('\u{AD}'.column_count(), 1);
+    assert_eq!('\u{301}'.column_count(), 0);
+    assert_eq!('\u{200D}'.column_count(), 0);
+    assert_eq!('\u{FE0F}'.column_count(), 0);
+    assert_eq!('\u{4E2D}'.column_count(), 2);
+    assert_eq!('\u{FF21}'.column_count(), 2);
+    assert_eq!('\u{2614}'.column_count(), 2);
+    assert_eq!('\u{1F600}'.column_count(), 2);
+    assert_eq!('\u{1FA70}'.column_count(), 2);
+    assert_eq!('\u{2A6D6}'.column_count(), 2);
+}
+
+#[test]
+fn test_column_count_of_graphemes() {
+    assert_eq!("e\u{301}".column_count(), 1);
+    assert_eq!("\u{4E2D}\u{6587}".column_count(), 4);
+    assert_eq!("\u{1100}\u{1161}\u{11A8}".column_count(), 2);
+}
@@ -0,0 +1,59 @@
+use makepad_code_editor::{
+    decoration::{Decoration, DecorationSet, DecorationType},
+    text::{Change, Drift, Edit, Length, Position},
+};
+
+#[test]
+fn test_delete_keeps_empty_decorations_at_either_end() {
+    let position = |byte_index| Position {
+        line_index: 0,
+        byte_index,
+    };
+    let mut decorations = DecorationSet::new();
+    decorations.add_decoration(Decoration::new(
+        0,
+        position(2),
+        position(2),
+        DecorationType::Error,
+    ));
+    decorations.add_decoration(Decoration::new(
+        1,
+        position(3),
+        position(4),
+        DecorationType::Warning,
+    ));
+    decorations.add_decoration(Decoration::new(
+        3,
+        position(5),
+        position(5),
+        DecorationType::Warning,
+    ));
+    decorations.add_decoration(Decoration::new(
+        2,
+        position(6),
+        position(8),
+        DecorationType::Error,
+    ));
+    decorations.apply_edit(&Edit {
+        change: Change::Delete(
+            position(2),
+            Length {
+                line_count: 0,
+                byte_count: 3,
+            },
+        ),
+        drift: Drift::Before,
+    });
+    let decorations = decorations
+        .iter()
+        .map(|decoration| (decoration.id, decoration.start(), decoration.end()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        decorations,
+        [
+            (0, position(2), position(2)),
+            (3, position(2), position(2)),
+            (2, position(3), position(5))
+        ]
+    );
+}
@@ -0,0 +1,819 @@
+use makepad_code_editor::{
+    decoration::DecorationSet,
+    history::NewGroup,
+    selection::{Affinity, Cursor, Selection},
+    session::SelectionMode,
+    text::Position,
+    CodeDocument, CodeSession, Settings,
+};
+use std::rc::Rc;
+
+#[test]
+fn test_reload_to_fewer_lines() {
+    let document = CodeDocument::new("abc\ndef\nghi".into(), DecorationSet::new());
+    let mut session = CodeSession::new(document.clone());
+    session.set_selection(
+        Position {
+            line_index: 2,
+            byte_index: 3,
+        },
+        Affinity::Before,
+        SelectionMode::Simple,
+        NewGroup::Yes,
+    );
+    document.set_text("x".into());
+    session.handle_changes();
+    let selection = session.selections()[0];
+    assert_eq!(
+        selection.cursor.position,
+        Position {
+            line_index: 0,
+            byte_index: 1,
+        }
+    );
+    assert_eq!(selection.anchor, selection.cursor.position);
+}
+
+#[test]
+fn test_find_matches() {
+    let document = CodeDocument::new("abab\nxab\nab".into(), DecorationSet::new());
+    let session = CodeSession::new(document);
+    let position = |line_index, byte_index| Position {
+        line_index,
+        byte_index,
+    };
+    assert_eq!(
+        session.find_matches("ab").collect::<Vec<_>>(),
+        vec![
+            position(0, 0)..position(0, 2),
+            position(0, 2)..position(0, 4),
+            position(1, 1)..position(1, 3),
+            position(2, 0)..position(2, 2),
+        ]
+    );
+    assert_eq!(
+        session.find_matches("ab\nxab\na").collect::<Vec<_>>(),
+        vec![position(0, 2)..position(2, 1)]
+    );
+    assert_eq!(
+        session.find_matches("ab").nth(1),
+        Some(position(0, 2)..position(0, 4))
+    );
+    assert_eq!(session.find_matches("").count(), 0);
+}
+
+#[test]
+fn test_word_range_at_uses_word_chars() {
+    let document = CodeDocument::new("foo-bar_baz $x".into(), DecorationSet::new());
+    let session = CodeSession::new(document);
+    let position = |byte_index| Position {
+        line_index: 0,
+        byte_index,
+    };
+    assert_eq!(
+        session.word_range_at(position(1)),
+        Some(position(0)..position(3))
+    );
+    assert_eq!(
+        session.word_range_at(position(5)),
+        Some(position(4)..position(11))
+    );
+    assert_eq!(
+        session.word_range_at(position(14)),
+        Some(position(13)..position(14))
+    );
+}
+
+#[test]
+fn test_text_in_range_clamps() {
+    let document = CodeDocument::new("abc\ndef".into(), DecorationSet::new());
+    let session = CodeSession::new(document);
+    let position = |line_index, byte_index| Position {
+        line_index,
+        byte_index,
+    };
+    assert_eq!(
+        session
+            .text_in_range(position(0, 1)..position(1, 2))
+            .to_string(),
+        "bc\nde"
+    );
+    assert_eq!(
+        session
+            .text_in_range(position(1, 1)..position(9, 9))
+            .to_string(),
+        "ef"
+    );
+    assert_eq!(
+        session
+            .text_in_range(position(1, 2)..position(0, 0))
+            .to_string(),
+        ""
+    );
+}
+
+#[test]
+fn test_insert_snippet_tab_stops() {
+    let document = CodeDocument::new("xy".into(), DecorationSet::new());
+    let mut session = CodeSession::new(document.clone());
+    let position = |line_index, byte_index| Position {
+        line_index,
+        byte_index,
+    };
+    let cursors = |session: &CodeSession| {
+        session
+            .selections()
+            .iter()
+            .map(|selection| selection.cursor.position)
+            .collect::<Vec<_>>()
+    };
+    session.set_selection(
+        position(0, 1),
+        Affinity::Before,
+        SelectionMode::Simple,
+        NewGroup::Yes,
+    );
+    session.insert_snippet("f($1, $1) {\n$2\n}$0 \\$");
+    assert_eq!(document.as_text().to_string(), "xf(, ) {\n\n} $y");
+    assert_eq!(cursors(&session), vec![position(0, 3), position(0, 5)]);
+    session.insert("a".into());
+    session.handle_changes();
+    assert_eq!(document.as_text().to_string(), "xf(a, a) {\n\n} $y");
+    assert!(session.next_snippet_stop());
+    assert_eq!(cursors(&session), vec![position(1, 0)]);
+    assert!(session.prev_snippet_stop());
+    assert_eq!(cursors(&session), vec![position(0, 4), position(0, 7)]);
+    assert!(session.next_snippet_stop());
+    assert!(session.next_snippet_stop());
+    assert_eq!(cursors(&session), vec![position(2, 1)]);
+    assert!(!session.next_snippet_stop());
+}
+
+#[test]
+fn test_composition_is_provisional() {
+    let document = CodeDocument::new("ab".into(), DecorationSet::new());
+    let mut session = CodeSession::new(document.clone());
+    let position = |byte_index| Position {
+        line_index: 0,
+        byte_index,
+    };
+    session.set_selection(
+        position(1),
+        Affinity::Before,
+        SelectionMode::Simple,
+        NewGroup::Yes,
+    );
+    session.set_composition("x".into());
+    session.set_composition("xy".into());
+    assert_eq!(document.as_text().to_string(), "axyb");
+    assert_eq!(session.composition_range(), Some(position(1)..position(3)));
+    assert_eq!(session.selections()[0].cursor.position, position(3));
+    assert!(session.cancel_composition());
+    assert_eq!(document.as_text().to_string(), "ab");
+    assert_eq!(session.composition_range(), None);
+    session.set_composition("z".into());
+    assert!(session.commit_composition());
+    session.handle_changes();
+    assert_eq!(document.as_text().to_string(), "azb");
+    assert!(!session.commit_composition());
+    session.set_composition("w".into());
+    assert!(session.undo());
+    session.handle_changes();
+    assert_eq!(document.as_text().to_string(), "ab");
+    assert_eq!(session.composition_range(), None);
+}
+
+#[test]
+fn test_edit_during_composition() {
+    let document = CodeDocument::new("ab".into(), DecorationSet::new());
+    let mut session = CodeSession::new(document.clone());
+    session.set_selection(
+        Position {
+            line_index: 0,
+            byte_index: 1,
+        },
+        Affinity::Before,
+        SelectionMode::Simple,
+        NewGroup::Yes,
+    );
+    session.set_composition("xyz".into());
+    session.insert("Q".into());
+    assert!(!session.cancel_composition());
+    session.handle_changes();
+    assert_eq!(document.as_text().to_string(), "axyzQb");
+    assert!(session.undo());
+    session.handle_changes();
+    assert_eq!(document.as_text().to_string(), "axyzb");
+    assert!(session.undo());
+    session.handle_changes();
+    assert_eq!(document.as_text().to_string(), "ab");
+    assert!(session.redo());
+    assert!(session.redo());
+    session.handle_changes();
+    assert_eq!(document.as_text().to_string(), "axyzQb");
+
+    let peer = CodeSession::new(document.clone());
+    session.set_composition("w".into());
+    peer.insert("P".into());
+    assert!(!session.cancel_composition());
+    assert!(peer.undo());
+    session.handle_changes();
+    assert_eq!(document.as_text().to_string(), "axyzQwb");
+}
+
+#[test]
+fn test_empty_document() {
+    let document = CodeDocument::new("".into(), DecorationSet::new());
+    let mut session = CodeSession::new(document.clone());
+    session.fold();
+    while session.update_folds() {}
+    session.unfold();
+    while session.update_folds() {}
+    assert_eq!(session.layout().find_first_line_ending_after_y(0.0), 0);
+    document.set_text("a\n    b\n    c".into());
+    session.handle_changes();
+    session.fold_to_level(1);
+    assert!(session.update_folds());
+    document.set_text("".into());
+    session.handle_changes();
+    while session.update_folds() {}
+    assert!(!session.is_line_folded(1));
+    assert_eq!(session.layout().find_first_line_ending_after_y(0.0), 0);
+}
+
+#[test]
+fn test_line_gutter_info_during_fold() {
+    let document = CodeDocument::new("a\n    b\n    c\nd".into(), DecorationSet::new());
+    let session = CodeSession::new(document);
+    session.fold_to_level(1);
+    assert!(session.update_folds());
+    let header = session.line_gutter_info(0);
+    assert!(header.fold_header && !header.is_folded);
+    let folding = session.line_gutter_info(1);
+    assert_eq!(folding.scale, 0.9);
+    assert_eq!(folding.height, 0.9);
+    assert!(!folding.fold_header && !folding.is_folded);
+    assert!((session.line_gutter_info(2).y - 1.9).abs() < 1e-9);
+    while session.update_folds() {}
+    let folded = session.line_gutter_info(2);
+    assert!(folded.is_folded);
+    assert_eq!(folded.scale, 0.1);
+    assert!(!session.line_gutter_info(3).fold_header);
+}
+
+#[test]
+fn test_commit_completion_is_one_undo_step() {
+    let document = CodeDocument::new("".into(), DecorationSet::new());
+    let mut session = CodeSession::new(document.clone());
+    let position = |byte_index| Position {
+        line_index: 0,
+        byte_index,
+    };
+    session.insert("le".into());
+    session.handle_changes();
+    session.commit_completion(position(0)..position(2), "length".into());
+    assert_eq!(document.as_text().to_string(), "length");
+    assert_eq!(session.selections()[0].cursor.position, position(6));
+    document.force_new_group();
+    session.insert(";".into());
+    session.handle_changes();
+    assert!(session.undo());
+    session.handle_changes();
+    assert_eq!(document.as_text().to_string(), "length");
+    assert!(session.undo());
+    session.handle_changes();
+    assert_eq!(document.as_text().to_string(), "");
+}
+
+#[test]
+fn test_selection_count() {
+    let document = CodeDocument::new("abc\ndef".into(), DecorationSet::new());
+    let session = CodeSession::new(document);
+    assert_eq!(session.selection_count(), 1);
+    assert!(!session.has_multiple_cursors());
+    session.add_selection(
+        Position {
+            line_index: 1,
+            byte_index: 2,
+        },
+        Affinity::Before,
+        SelectionMode::Simple,
+    );
+    assert_eq!(session.selection_count(), 2);
+    assert!(session.has_multiple_cursors());
+    session.collapse_to_primary();
+    assert_eq!(session.selection_count(), 1);
+}
+
+#[test]
+fn test_peer_edit_merges_cursors() {
+    let document = CodeDocument::new("abcdef".into(), DecorationSet::new());
+    let session_0 = CodeSession::new(document.clone());
+    let mut session_1 = CodeSession::new(document.clone());
+    let position = |byte_index| Position {
+        line_index: 0,
+        byte_index,
+    };
+    session_1.set_selection(
+        position(1),
+        Affinity::Before,
+        SelectionMode::Simple,
+        NewGroup::Yes,
+    );
+    session_1.add_selection(position(3), Affinity::Before, SelectionMode::Simple);
+    session_1.add_selection(position(5), Affinity::Before, SelectionMode::Simple);
+    assert_eq!(session_1.last_added_selection_index(), Some(2));
+    session_0.commit_completion(position(0)..position(6), "x".into());
+    session_1.handle_changes();
+    assert_eq!(document.as_text().to_string(), "x");
+    assert_eq!(session_1.selection_count(), 1);
+    assert_eq!(session_1.last_added_selection_index(), Some(0));
+    assert_eq!(session_1.primary_selection().cursor.position, position(1));
+}
+
+#[test]
+fn test_toggle_wrap_remembers_column() {
+    let document = CodeDocument::new("abc".into(), DecorationSet::new());
+    let session = CodeSession::new(document);
+    session.toggle_wrap(80);
+    assert_eq!(session.wrap_column(), Some(80));
+    session.set_wrap_column(Some(40));
+    session.toggle_wrap(80);
+    assert_eq!(session.wrap_column(), None);
+    session.toggle_wrap(80);
+    assert_eq!(session.wrap_column(), Some(40));
+}
+
+#[test]
+fn test_move_to_matching_bracket() {
+    let document = CodeDocument::new("f(a, [b])\nx()".into(), DecorationSet::new());
+    let session = CodeSession::new(document);
+    let position = |line_index, byte_index| Position {
+        line_index,
+        byte_index,
+    };
+    let cursor = |session: &CodeSession| session.selections()[0].cursor.position;
+    session.set_selection(
+        position(0, 1),
+        Affinity::Before,
+        SelectionMode::Simple,
+        NewGroup::Yes,
+    );
+    session.move_to_matching_bracket(true);
+    assert_eq!(cursor(&session), position(0, 9));
+    session.move_to_matching_bracket(true);
+    assert_eq!(cursor(&session), position(0, 1));
+    session.set_selection(
+        position(0, 6),
+        Affinity::Before,
+        SelectionMode::Simple,
+        NewGroup::Yes,
+    );
+    session.move_to_matching_bracket(false);
+    assert_eq!(cursor(&session), position(0, 7));
+    assert_eq!(session.selections()[0].anchor, position(0, 6));
+    session.set_selection(
+        position(1, 0),
+        Affinity::Before,
+        SelectionMode::Simple,
+        NewGroup::Yes,
+    );
+    session.move_to_matching_bracket(true);
+    assert_eq!(cursor(&session), position(1, 0));
+    session.set_selection(
+        position(1, 2),
+        Affinity::Before,
+        SelectionMode::Simple,
+        NewGroup::Yes,
+    );
+    session.move_to_matching_bracket(true);
+    assert_eq!(cursor(&session), position(1, 1));
+    session.move_to_matching_bracket(true);
+    assert_eq!(cursor(&session), position(1, 3));
+    session.move_to_matching_bracket(true);
+    assert_eq!(cursor(&session), position(1, 1));
+}
+
+#[test]
+fn test_selection_contains_and_overlaps() {
+    let selection = |anchor, cursor| Selection {
+        cursor: Cursor {
+            position: Position {
+                line_index: 0,
+                byte_index: cursor,
+            },
+            affinity: Affinity::Before,
+            preferred_column_index: None,
+        },
+        anchor: Position {
+            line_index: 0,
+            byte_index: anchor,
+        },
+    };
+    let position = |byte_index| Position {
+        line_index: 0,
+        byte_index,
+    };
+    assert!(selection(4, 1).contains(position(1)));
+    assert!(selection(4, 1).contains(position(4)));
+    assert!(!selection(4, 1).contains(position(5)));
+    assert!(selection(2, 2).contains(position(2)));
+    assert!(selection(1, 4).overlaps(selection(6, 3)));
+    assert!(selection(6, 3).overlaps(selection(1, 4)));
+    assert!(!selection(1, 4).overlaps(selection(4, 6)));
+    assert!(selection(1, 4).overlaps(selection(4, 4)));
+    assert!(!selection(1, 4).overlaps(selection(5, 5)));
+}
+
+#[test]
+fn test_matching_bracket_skips_strings_and_comments() {
+    let document = CodeDocument::new("{ \"}\" '{' // }\n}".into(), DecorationSet::new());
+    let session = CodeSession::new(document);
+    let position = |line_index, byte_index| Position {
+        line_index,
+        byte_index,
+    };
+    session.set_selection(
+        position(0, 0),
+        Affinity::Before,
+        SelectionMode::Simple,
+        NewGroup::Yes,
+    );
+    session.move_to_matching_bracket(true);
+    assert_eq!(session.selections()[0].cursor.position, position(1, 1));
+    session.set_selection(
+        position(0, 3),
+        Affinity::Before,
+        SelectionMode::Simple,
+        NewGroup::Yes,
+    );
+    session.move_to_matching_bracket(true);
+    assert_eq!(session.selections()[0].cursor.position, position(0, 3));
+}
+
+#[test]
+fn test_select_all_occurrences() {
+    let document = CodeDocument::new("foo bar\nfoo foobar".into(), DecorationSet::new());
+    let session = CodeSession::new(document);
+    let position = |line_index, byte_index| Position {
+        line_index,
+        byte_index,
+    };
+    let ranges = |session: &CodeSession| {
+        session
+            .selections()
+            .iter()
+            .map(|selection| selection.start()..selection.end())
+            .collect::<Vec<_>>()
+    };
+    session.add_selection(position(0, 5), Affinity::Before, SelectionMode::Simple);
+    session.select_all_occurrences();
+    assert_eq!(
+        ranges(&session),
+        vec![
+            position(0, 0)..position(0, 0),
+            position(0, 4)..position(0, 7),
+            position(1, 7)..position(1, 10),
+        ]
+    );
+    assert_eq!(session.last_added_selection_index(), Some(2));
+    session.set_primary_selection(0);
+    session.select_all_occurrences();
+    assert_eq!(session.selection_count(), 5);
+    assert_eq!(ranges(&session)[0], position(0, 0)..position(0, 3));
+    assert_eq!(session.last_added_selection_index(), Some(3));
+}
+
+#[test]
+fn test_set_settings_keeps_folds() {
+    let document = CodeDocument::new("a\n\tbc".into(), DecorationSet::new());
+    let mut session = CodeSession::new(document);
+    session.fold_to_level(1);
+    while session.update_folds() {}
+    assert_eq!(session.layout().line(1).column_count(), 6);
+    assert_eq!(session.layout().line(1).fold(), 4);
+    session.set_settings(Rc::new(Settings {
+        tab_column_count: 8,
+        ..Settings::default()
+    }));
+    assert_eq!(session.settings().tab_column_count, 8);
+    assert!(session.is_line_folded(1));
+    assert_eq!(session.layout().line(1).column_count(), 10);
+    assert_eq!(session.layout().line(1).fold(), 8);
+}
+
+#[test]
+fn test_set_settings_refolds_to_level() {
+    let document = CodeDocument::new("a\n        b".into(), DecorationSet::new());
+    let mut session = CodeSession::new(document);
+    session.fold();
+    while session.update_folds() {}
+    assert_eq!(session.layout().line(1).fold(), 8);
+    session.set_settings(Rc::new(Settings {
+        indent_column_count: Some(2),
+        ..Settings::default()
+    }));
+    assert!(session.is_line_folded(1));
+    assert_eq!(session.layout().line(1).fold(), 4);
+    session.set_settings(Rc::new(Settings {
+        indent_column_count: Some(2),
+        fold_level: 3,
+        ..Settings::default()
+    }));
+    assert_eq!(session.layout().line(1).fold(), 6);
+}
+
+#[test]
+fn test_delete_and_backspace_remove_whole_graphemes() {
+    let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+    let thumbs_up = "\u{1F44D}\u{1F3FD}";
+    let flag = "\u{1F1F3}\u{1F1F1}";
+    let document = CodeDocument::new(
+        format!("a{}{}{}b", family, thumbs_up, flag).into(),
+        DecorationSet::new(),
+    );
+    let mut session = CodeSession::new(document.clone());
+    session.set_selection(
+        Position {
+            line_index: 0,
+            byte_index: 1 + family.len() + thumbs_up.len(),
+        },
+        Affinity::Before,
+        SelectionMode::Simple,
+        NewGroup::Yes,
+    );
+    session.backspace();
+    session.handle_changes();
+    assert_eq!(
+        document.as_text().to_string(),
+        format!("a{}{}b", family, flag)
+    );
+    session.delete();
+    session.handle_changes();
+    assert_eq!(document.as_text().to_string(), format!("a{}b", family));
+    session.backspace();
+    session.handle_changes();
+    assert_eq!(document.as_text().to_string(), "ab");
+}
+
+#[test]
+fn test_snapshot_is_unaffected_by_edits() {
+    let document = CodeDocument::from_text("abc".into());
+    let mut session = CodeSession::new(document.clone());
+    let snapshot = document.snapshot();
+    session.insert("x".into());
+    session.handle_changes();
+    assert_eq!(document.as_text().to_string(), "xabc");
+    let handle = std::thread::spawn(move || snapshot.to_string());
+    assert_eq!(handle.join().unwrap(), "abc");
+}
+
+#[test]
+fn test_fold_animation_finished_is_reported_once() {
+    let document = CodeDocument::new("a\n    b\nc".into(), DecorationSet::new());
+    let session = CodeSession::new(document);
+    assert!(!session.is_fold_animating());
+    session.fold_to_level(1);
+    assert!(session.is_fold_animating());
+    assert!(session.update_folds());
+    assert!(!session.take_fold_animation_finished());
+    while session.update_folds() {}
+    assert!(!session.is_fold_animating());
+    assert!(session.take_fold_animation_finished());
+    assert!(!session.take_fold_animation_finished());
+    assert!(!session.update_folds());
+    assert!(!session.take_fold_animation_finished());
+    // A finished animation that nobody took is stale once a new one starts.
+    session.unfold();
+    while session.update_folds() {}
+    session.fold_to_level(1);
+    assert!(!session.take_fold_animation_finished());
+}
+
+#[test]
+fn test_move_by_paragraph() {
+    let document = CodeDocument::new("a\nb\n  \n\nc\nd".into(), DecorationSet::new());
+    let session = CodeSession::new(document);
+    let position = |line_index, byte_index| Position {
+        line_index,
+        byte_index,
+    };
+    let cursor = |session: &CodeSession| session.selections()[0].cursor.position;
+    session.move_to_next_paragraph(true);
+    assert_eq!(cursor(&session), position(2, 0));
+    session.move_to_next_paragraph(false);
+    assert_eq!(cursor(&session), position(5, 1));
+    assert_eq!(session.selections()[0].anchor, position(2, 0));
+    session.move_to_prev_paragraph(true);
+    assert_eq!(cursor(&session), position(3, 0));
+    session.move_to_prev_paragraph(true);
+    assert_eq!(cursor(&session), position(0, 0));
+    session.set_selection(
+        position(4, 1),
+        Affinity::Before,
+        SelectionMode::Simple,
+        NewGroup::Yes,
+    );
+    session.move_to_prev_paragraph(true);
+    assert_eq!(cursor(&session), position(3, 0));
+}
+
+#[test]
+fn test_indent_and_outdent_keep_tabs() {
+    let document = CodeDocument::new("\tfoo\n\t\tbar\n\t  baz".into(), DecorationSet::new());
+    let session = CodeSession::new(document.clone());
+    session.set_selection(
+        Position::zero(),
+        Affinity::Before,
+        SelectionMode::All,
+        NewGroup::Yes,
+    );
+    session.indent();
+    assert_eq!(
+        document.as_text().to_string(),
+        "\t    foo\n\t\t    bar\n\t    baz"
+    );
+    session.outdent();
+    session.outdent();
+    assert_eq!(document.as_text().to_string(), "foo\n\tbar\nbaz");
+}
+
+#[test]
+fn test_horizontal_movement_across_wrap() {
+    let document = CodeDocument::new("aaa bbb".into(), DecorationSet::new());
+    let session = CodeSession::new(document);
+    session.set_wrap_column(Some(5));
+    session.set_selection(
+        Position {
+            line_index: 0,
+            byte_index: 3,
+        },
+        Affinity::Before,
+        SelectionMode::Simple,
+        NewGroup::Yes,
+    );
+    let cursor = |session: &CodeSession| {
+        let cursor = session.selections()[0].cursor;
+        (cursor.position.byte_index, cursor.affinity)
+    };
+    session.move_right(true);
+    assert_eq!(cursor(&session), (4, Affinity::Before));
+    session.move_right(true);
+    assert_eq!(cursor(&session), (4, Affinity::After));
+    session.move_right(true);
+    assert_eq!(cursor(&session), (5, Affinity::Before));
+    session.move_left(true);
+    assert_eq!(cursor(&session), (4, Affinity::After));
+    session.move_left(true);
+    assert_eq!(cursor(&session), (4, Affinity::Before));
+    session.move_left(true);
+    assert_eq!(cursor(&session), (3, Affinity::After));
+}
+
+#[test]
+fn test_layout_advances_tabs_to_tab_stops() {
+    let document = CodeDocument::new(" \tab\n\t  \tc".into(), DecorationSet::new());
+    let session = CodeSession::new(document);
+    let layout = session.layout();
+    assert_eq!(
+        layout.line(0).logical_to_grid_position(2, Affinity::Before),
+        (0, 4)
+    );
+    assert_eq!(layout.line(0).column_count(), 6);
+    assert_eq!(
+        layout.line(1).logical_to_grid_position(4, Affinity::Before),
+        (0, 8)
+    );
+    assert_eq!(layout.line(1).grid_to_logical_position(0, 5), (2, Affinity::After));
+}
+
+#[test]
+fn test_reflow_paragraph_keeps_boundaries() {
+    let document = CodeDocument::new(
+        "// aa bb\n// cc dd ee\n//\n// ff\n    gg hh\n    ii".into(),
+        DecorationSet::new(),
+    );
+    let mut session = CodeSession::new(document.clone());
+    session.set_settings(Rc::new(Settings {
+        line_comment: Some("//".to_string()),
+        ..Settings::default()
+    }));
+    session.reflow_paragraph(11);
+    session.handle_changes();
+    assert_eq!(
+        document.as_text().to_string(),
+        "// aa bb cc\n// dd ee\n//\n// ff\n    gg hh\n    ii"
+    );
+    session.set_selection(
+        Position {
+            line_index: 5,
+            byte_index: 0,
+        },
+        Affinity::Before,
+        SelectionMode::Simple,
+        NewGroup::Yes,
+    );
+    session.reflow_paragraph(80);
+    session.handle_changes();
+    assert_eq!(
+        document.as_text().to_string(),
+        "// aa bb cc\n// dd ee\n//\n// ff\n    gg hh ii"
+    );
+    session.set_selection(
+        Position {
+            line_index: 2,
+            byte_index: 0,
+        },
+        Affinity::Before,
+        SelectionMode::Simple,
+        NewGroup::Yes,
+    );
+    session.reflow_paragraph(80);
+    session.handle_changes();
+    assert_eq!(
+        document.as_text().to_string(),
+        "// aa bb cc\n// dd ee\n//\n// ff\n    gg hh ii"
+    );
+    assert!(session.undo());
+    session.handle_changes();
+    assert_eq!(
+        document.as_text().to_string(),
+        "// aa bb cc\n// dd ee\n//\n// ff\n    gg hh\n    ii"
+    );
+}
+
+#[test]
+fn test_merged_selections_keep_direction() {
+    let document = CodeDocument::new("abc def".into(), DecorationSet::new());
+    let session = CodeSession::new(document);
+    let position = |byte_index| Position {
+        line_index: 0,
+        byte_index,
+    };
+    session.set_selection(position(7), Affinity::Before, SelectionMode::Simple, NewGroup::Yes);
+    session.move_to(position(4), Affinity::After, NewGroup::No);
+    session.add_selection(position(1), Affinity::After, SelectionMode::Simple);
+    let selections = session.merged_selections();
+    assert_eq!(selections.len(), 2);
+    assert_eq!(selections[1].anchor, position(7));
+    assert_eq!(selections[1].cursor.position, position(4));
+}
+
+#[test]
+fn test_enter_continues_line_comments() {
+    let enter_at_end = |text: &str| {
+        let document = CodeDocument::new(text.into(), DecorationSet::new());
+        let mut session = CodeSession::new(document.clone());
+        session.set_settings(Rc::new(Settings {
+            line_comment: Some("//".to_string()),
+            ..Settings::default()
+        }));
+        session.set_selection(
+            Position {
+                line_index: 0,
+                byte_index: text.len(),
+            },
+            Affinity::Before,
+            SelectionMode::Simple,
+            NewGroup::Yes,
+        );
+        session.enter();
+        session.handle_changes();
+        let text = document.as_text().to_string();
+        text
+    };
+    assert_eq!(enter_at_end("    /// doc"), "    /// doc\n    /// ");
+    assert_eq!(enter_at_end("//-----"), "//-----\n//");
+    assert_eq!(enter_at_end("// "), "");
+}
+
+#[test]
+fn test_autoindent_expands_tabs() {
+    let document = CodeDocument::new("\tif x {\n\t\tfoo\n\t\t".into(), DecorationSet::new());
+    let session = CodeSession::new(document.clone());
+    session.set_selection(
+        Position {
+            line_index: 2,
+            byte_index: 2,
+        },
+        Affinity::Before,
+        SelectionMode::Simple,
+        NewGroup::Yes,
+    );
+    session.insert("}".into());
+    assert_eq!(document.as_text().to_string(), "\tif x {\n\t\tfoo\n\t}");
+    session.set_selection(
+        Position {
+            line_index: 0,
+            byte_index: 7,
+        },
+        Affinity::Before,
+        SelectionMode::Simple,
+        NewGroup::Yes,
+    );
+    session.enter();
+    assert_eq!(
+        document.as_text().to_string(),
+        "\tif x {\n        \n\t\tfoo\n\t}"
+    );
+}
@@ -0,0 +1,57 @@
+use makepad_code_editor::str::StrExt;
+
+#[test]
+fn test_graphemes_are_extended_clusters() {
+    let family = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}";
+    let thumbs_up = "\u{1F44D}\u{1F3FD}";
+    let flag = "\u{1F1F3}\u{1F1F1}";
+    let string = format!("a{}{}{}e\u{301}", family, thumbs_up, flag);
+    assert_eq!(
+        string.graphemes().collect::<Vec<_>>(),
+        vec!["a", family, thumbs_up, flag, "e\u{301}"]
+    );
+    assert_eq!(string.graphemes().next_back(), Some("e\u{301}"));
+    assert_eq!(
+        string.grapheme_indices().nth(2),
+        Some((1 + family.len(), thumbs_up))
+    );
+}
+
+#[test]
+fn test_column_count_at_mixes_tabs_and_spaces() {
+    assert_eq!("\tfoo".column_count_at(0, 4), 7);
+    assert_eq!(" \tfoo".column_count_at(0, 4), 7);
+    assert_eq!("   \t".column_count_at(0, 4), 4);
+    assert_eq!("    \t".column_count_at(0, 4), 8);
+    assert_eq!("\t \t".column_count_at(0, 4), 8);
+    assert_eq!("\t".column_count_at(2, 4), 2);
+    assert_eq!(" \t".column_count_at(0, 8), 8);
+}
+
+#[test]
+fn test_column_count_at_mixes_tabs_and_wide_chars() {
+    assert_eq!("\u{4E2D}\t".column_count_at(0, 4), 4);
+    assert_eq!("\u{4E2D}\u{6587}\tx".column_count_at(0, 4), 9);
+    assert_eq!("a\u{4E2D}\tx".column_count_at(0, 4), 5);
+    assert_eq!("\t\u{1F600}".column_count_at(1, 4), 5);
+    assert_eq!("e\u{301}\t".column_count_at(0, 4), 4);
+}
+
+#[test]
+fn test_graphemes_in_range_keeps_clusters_whole() {
+    // A space followed by a combining mark forms a single grapheme, but a whitespace boundary
+    // (and hence a wrap) falls between them.
+    let string = "a \u{301}b";
+    assert_eq!(
+        string.graphemes_in_range(0..2).collect::<Vec<_>>(),
+        ["a", " \u{301}"]
+    );
+    assert_eq!(
+        string.graphemes_in_range(2..string.len()).collect::<Vec<_>>(),
+        ["b"]
+    );
+    assert_eq!(
+        string.graphemes_in_range(0..string.len()).collect::<Vec<_>>(),
+        string.graphemes().collect::<Vec<_>>()
+    );
+}
@@ -0,0 +1,85 @@
+use makepad_code_editor::text::{Position, Text};
+
+#[test]
+fn test_lsp_positions_round_trip() {
+    let text = Text::from("a\u{1F600}b\n\u{E9}\u{10348}c\n");
+    for (line_index, line) in text.as_lines().iter().enumerate() {
+        for (byte_index, _) in line.char_indices().chain([(line.len(), ' ')]) {
+            let position = Position {
+                line_index,
+                byte_index,
+            };
+            let (lsp_line_index, utf16_column_index) = text.position_to_lsp(position);
+            assert_eq!(
+                text.lsp_to_position(lsp_line_index, utf16_column_index),
+                position
+            );
+        }
+    }
+}
+
+#[test]
+fn test_lsp_positions_count_astral_chars_twice() {
+    let text = Text::from("a\u{1F600}b");
+    let position = |byte_index| Position {
+        line_index: 0,
+        byte_index,
+    };
+    assert_eq!(text.position_to_lsp(position(1)), (0, 1));
+    assert_eq!(text.position_to_lsp(position(5)), (0, 3));
+    assert_eq!(text.position_to_lsp(position(6)), (0, 4));
+    assert_eq!(text.lsp_to_position(0, 3), position(5));
+}
+
+#[test]
+fn test_position_to_lsp_clamps_line() {
+    let text = Text::from("ab\n\u{1F600}");
+    assert_eq!(
+        text.position_to_lsp(Position {
+            line_index: 5,
+            byte_index: 0,
+        }),
+        (1, 2)
+    );
+}
+
+#[test]
+fn test_offsets_round_trip() {
+    let text = Text::from("ab\n\n\u{E9}c\n");
+    let string = text.to_string();
+    for offset in 0..=string.len() {
+        if !string.is_char_boundary(offset) {
+            continue;
+        }
+        let position = text.offset_to_position(offset);
+        assert_eq!(text.position_to_offset(position), offset);
+        let length = Text::from(&string[..offset]).length();
+        assert_eq!(position, Position::zero() + length);
+    }
+}
+
+#[test]
+fn test_offsets_clamp() {
+    let text = Text::from("ab\nc");
+    assert_eq!(
+        text.offset_to_position(100),
+        Position {
+            line_index: 1,
+            byte_index: 1,
+        }
+    );
+    assert_eq!(
+        text.position_to_offset(Position {
+            line_index: 0,
+            byte_index: 100,
+        }),
+        2
+    );
+    assert_eq!(
+        text.position_to_offset(Position {
+            line_index: 100,
+            byte_index: 0,
+        }),
+        4
+    );
+}
@@ -0,0 +1,150 @@
+use makepad_code_editor::{
+    decoration::DecorationSet,
+    text::{Change, Position, Text},
+    token::TokenKind,
+    tokenizer::{State, KEYWORDS},
+    CodeDocument, RustTokenizer, Token, TokenCache, Tokenizer,
+};
+
+fn token_kinds(tokenizer: &mut RustTokenizer, line: &str) -> Vec<TokenKind> {
+    let mut kinds = Vec::new();
+    let mut state = State::default();
+    let mut byte_index = 0;
+    while byte_index < line.len() {
+        let (token, next_state) = tokenizer.next_token(&line[byte_index..], state);
+        state = next_state;
+        byte_index += token.len;
+        if token.kind != TokenKind::Whitespace {
+            kinds.push(token.kind);
+        }
+    }
+    kinds
+}
+
+#[test]
+fn test_with_keywords() {
+    let line = "let query = select(x) if";
+    assert_eq!(
+        token_kinds(&mut RustTokenizer::default(), line),
+        vec![
+            TokenKind::OtherKeyword,
+            TokenKind::Identifier,
+            TokenKind::Punctuator,
+            TokenKind::Function,
+            TokenKind::Delimiter,
+            TokenKind::Identifier,
+            TokenKind::Delimiter,
+            TokenKind::BranchKeyword,
+        ]
+    );
+    let mut keywords = KEYWORDS.to_vec();
+    keywords.retain(|&keyword| keyword != "let");
+    keywords.push("select");
+    assert_eq!(
+        token_kinds(&mut RustTokenizer::with_keywords(&keywords), line),
+        vec![
+            TokenKind::Identifier,
+            TokenKind::Identifier,
+            TokenKind::Punctuator,
+            TokenKind::OtherKeyword,
+            TokenKind::Delimiter,
+            TokenKind::Identifier,
+            TokenKind::Delimiter,
+            TokenKind::BranchKeyword,
+        ]
+    );
+}
+
+#[test]
+fn test_edit_inside_block_comment() {
+    let mut text = Text::from("a\n/* b\nc */\nd");
+    let mut cache = TokenCache::new(4);
+    let mut tokens = vec![Vec::new(); 4];
+    cache.update(&text, &mut tokens);
+    assert!(tokens.iter().all(|tokens| !tokens.is_empty()));
+    // Lines that are not lexed again keep these empty token lists.
+    tokens[2].clear();
+    tokens[3].clear();
+    let change = Change::Insert(
+        Position {
+            line_index: 1,
+            byte_index: 4,
+        },
+        "x".into(),
+    );
+    cache.apply_change(&change);
+    text.apply_change(change);
+    cache.update(&text, &mut tokens);
+    assert!(tokens[2].is_empty() && tokens[3].is_empty());
+    // Closing the comment early changes the state at the start of the next line only.
+    let change = Change::Insert(
+        Position {
+            line_index: 1,
+            byte_index: 5,
+        },
+        " */".into(),
+    );
+    cache.apply_change(&change);
+    text.apply_change(change);
+    cache.update(&text, &mut tokens);
+    assert_eq!(tokens[2][0].kind, TokenKind::Identifier);
+    assert!(tokens[3].is_empty());
+}
+
+#[derive(Debug)]
+struct WordTokenizer;
+
+impl Tokenizer for WordTokenizer {
+    type State = ();
+
+    fn next_token(&mut self, line: &str, _state: ()) -> (Token, ()) {
+        let len = match line.find(' ') {
+            Some(0) => 1,
+            Some(index) => index,
+            None => line.len(),
+        };
+        // Deliberately returns an empty token for a line starting with `!`.
+        let len = if line.starts_with('!') { 0 } else { len };
+        (
+            Token {
+                len,
+                kind: TokenKind::Identifier,
+            },
+            (),
+        )
+    }
+}
+
+#[test]
+fn test_document_with_tokenizer() {
+    let document = CodeDocument::with_tokenizer(
+        "ab cd\n!ef".into(),
+        DecorationSet::new(),
+        WordTokenizer,
+    );
+    let layout = document.layout();
+    assert_eq!(
+        layout.tokens[0],
+        vec![
+            Token {
+                len: 2,
+                kind: TokenKind::Identifier,
+            },
+            Token {
+                len: 1,
+                kind: TokenKind::Identifier,
+            },
+            Token {
+                len: 2,
+                kind: TokenKind::Identifier,
+            },
+        ]
+    );
+    assert_eq!(
+        layout.tokens[1],
+        vec![Token {
+            len: 3,
+            kind: TokenKind::Unknown,
+        }]
+    );
+}
@@ -12,6 +12,7 @@ pub mod live_registry;
 pub mod live_expander;
 pub mod live_ptr;
 pub mod live_eval;
+pub mod live_formatter;
 pub mod live_component;
 pub mod live_node_cbor;
 //pub mod live_node_cbor;
@@ -38,6 +39,9 @@ pub use {
         live_eval::{
             live_eval_value,
         },
+        live_formatter::{
+            format_live_source,
+        },
         live_registry::{
             LiveFileChange,
             LiveRegistry,
@@ -97,6 +101,8 @@ pub use {
         makepad_live_tokenizer::{LiveErrorOrigin, live_error_origin},
         live_error::{
             LiveError,
+            LiveErrorSeverity,
+            LiveDiagnostic,
             LiveFileError
         },
         live_document::{LiveOriginal, LiveExpanded}
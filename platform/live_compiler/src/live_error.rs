@@ -1,6 +1,8 @@
 use {
     std::fmt,
     crate::live_token::LiveTokenId,
+    crate::live_ptr::LiveFileId,
+    crate::live_registry::LiveRegistry,
     crate::span::{TextSpan,TokenSpan},
     makepad_live_tokenizer::{LiveErrorOrigin},
 };
@@ -38,13 +40,30 @@ impl From<LiveTokenId> for LiveErrorSpan {
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LiveErrorSeverity {
+    Error,
+    Warning
+}
+
 #[derive(Clone)]
 pub struct LiveError {
     pub origin: LiveErrorOrigin,
+    pub severity: LiveErrorSeverity,
     pub span: LiveErrorSpan,
     pub message: String,
 }
 
+// structured form of a LiveError for editor hosts, positions are 0 based (line, column)
+#[derive(Clone, Debug, PartialEq)]
+pub struct LiveDiagnostic {
+    pub file_id: LiveFileId,
+    pub start: (u32, u32),
+    pub end: (u32, u32),
+    pub message: String,
+    pub severity: LiveErrorSeverity,
+}
+
 #[derive(Clone, PartialEq)]
 pub struct LiveFileError {
     pub origin: LiveErrorOrigin,
@@ -72,6 +91,31 @@ impl fmt::Display for LiveFileError {
 
 impl LiveError{
     
+    pub fn to_diagnostic(&self, live_registry: &LiveRegistry) -> LiveDiagnostic {
+        let span = match &self.span {
+            LiveErrorSpan::Text(span) => *span,
+            LiveErrorSpan::Token(token_span) => if token_span.token_id.file_id().is_some() {
+                let start = live_registry.token_id_to_span(token_span.token_id);
+                let last = LiveTokenId::new(start.file_id, token_span.token_id.token_index() + token_span.len.max(1) - 1);
+                TextSpan {
+                    file_id: start.file_id,
+                    start: start.start,
+                    end: live_registry.token_id_to_span(last).end
+                }
+            }
+            else {
+                TextSpan::default()
+            }
+        };
+        LiveDiagnostic {
+            file_id: span.file_id,
+            start: (span.start.line, span.start.column),
+            end: (span.end.line, span.end.column),
+            message: self.message.clone(),
+            severity: self.severity
+        }
+    }
+    
     pub fn into_live_file_error(self, file:&str)->LiveFileError{
         LiveFileError {
             origin: self.origin.clone(),
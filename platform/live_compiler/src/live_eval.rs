@@ -7,7 +7,7 @@ pub use {
         makepad_math::*,
         makepad_live_id::*,
         makepad_live_tokenizer::{LiveErrorOrigin, live_error_origin},
-        live_error::{LiveError},
+        live_error::{LiveError, LiveErrorSeverity},
         live_node_vec::*,
         live_registry::{LiveRegistry,LiveScopeTarget},
         live_node::*
@@ -50,6 +50,7 @@ impl LiveError {
     fn eval_error(origin: LiveErrorOrigin, index: usize, nodes: &[LiveNode], message: String)->Self{
         LiveError {
             origin,
+            severity: LiveErrorSeverity::Error,
             message,
             span: nodes[index].origin.token_id().unwrap().into()
         }
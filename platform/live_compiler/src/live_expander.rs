@@ -1,13 +1,17 @@
 use std::cmp::Ordering;
 
 use {
-    std::sync::Arc,
+    std::{
+        collections::HashSet,
+        sync::Arc,
+    },
     crate::{
         makepad_live_id::*,
         makepad_live_tokenizer::{live_error_origin, LiveErrorOrigin},
         live_ptr::{LiveFileId, LivePtr, LiveFileGeneration},
-        live_error::{LiveError},
+        live_error::{LiveError, LiveErrorSeverity},
         live_eval::live_eval_value,
+        live_token::LiveToken,
         live_document::{LiveOriginal, LiveExpanded},
         live_node::{LiveValue, LiveNode, LiveFieldKind, LivePropType},
         live_node_vec::{LiveNodeSliceApi, LiveNodeVecApi},
@@ -109,6 +113,7 @@ impl<'a> LiveExpander<'a> {
                     if !found {
                         self.errors.push(LiveError {
                             origin: live_error_origin!(),
+                            severity: LiveErrorSeverity::Error,
                             span: in_node.origin.token_id().unwrap().into(),
                             message: format!("Import statement nothing found {}::{} as {}", live_import.module_id, live_import.import_id, in_node.id)
                         });
@@ -131,6 +136,7 @@ impl<'a> LiveExpander<'a> {
                     if in_node.origin.edit_info().is_some() {
                         self.errors.push(LiveError {
                             origin: live_error_origin!(),
+                            severity: LiveErrorSeverity::Error,
                             span: in_doc.token_id_to_span(in_node.origin.token_id().unwrap()).into(),
                             message: format!("Cannot define edit info after first prop def of {}", in_node.id)
                         });
@@ -297,6 +303,7 @@ impl<'a> LiveExpander<'a> {
                                             if live_type != old_live_type {
                                                 self.errors.push(LiveError {
                                                     origin: live_error_origin!(),
+                                                    severity: LiveErrorSeverity::Error,
                                                     span: in_doc.token_id_to_span(in_node.origin.token_id().unwrap()).into(),
                                                     message: format!("Class override with wrong type {}", in_node.id)
                                                 });
@@ -338,6 +345,7 @@ impl<'a> LiveExpander<'a> {
                     else if !Self::is_baseclass(*clone) { //if !self.live_registry.ignore_no_dsl.contains(clone) {
                         self.errors.push(LiveError {
                             origin: live_error_origin!(),
+                            severity: LiveErrorSeverity::Error,
                             span: in_doc.token_id_to_span(in_node.origin.token_id().unwrap()).into(),
                             message: format!("Can't find live definition of {} did you forget to call live_design for it?", clone)
                         });
@@ -413,6 +421,7 @@ impl<'a> LiveExpander<'a> {
                                 else if !lti.live_ignore {
                                     self.errors.push(LiveError {
                                         origin: live_error_origin!(),
+                                        severity: LiveErrorSeverity::Error,
                                         span: in_doc.token_id_to_span(in_node.origin.token_id().unwrap()).into(),
                                         message: format!("Can't find live definition of {} did you forget to call live_design for it?", lti.type_name)
                                     });
@@ -452,6 +461,7 @@ impl<'a> LiveExpander<'a> {
                                 else if !lti.live_ignore && lti.type_name != LiveId(0) {
                                     self.errors.push(LiveError {
                                         origin: live_error_origin!(),
+                                        severity: LiveErrorSeverity::Error,
                                         span: in_doc.token_id_to_span(in_node.origin.token_id().unwrap()).into(),
                                         message: format!("Typename {}, not defined in file where it was expected", lti.type_name)
                                     });
@@ -461,6 +471,7 @@ impl<'a> LiveExpander<'a> {
                         else if !lti.live_ignore {
                             self.errors.push(LiveError {
                                 origin: live_error_origin!(),
+                                severity: LiveErrorSeverity::Error,
                                 span: in_doc.token_id_to_span(in_node.origin.token_id().unwrap()).into(),
                                 message: format!("Can't find live definition of {} did you forget to call live_design for it?", lti.type_name)
                             });
@@ -486,6 +497,7 @@ impl<'a> LiveExpander<'a> {
             in_index += 1;
         }
         out_doc.nodes.push(in_doc.nodes.last().unwrap().clone());
+        self.check_unused_imports(in_doc);
         // this stores the node index on nodes that don't have a node index
         for i in 1..out_doc.nodes.len() {
             if out_doc.nodes[i].value.is_dsl() {
@@ -497,5 +509,109 @@ impl<'a> LiveExpander<'a> {
         }
     }
     
+    // an import counts as used when its id shows up as an ident anywhere outside
+    // the import statements, which includes the DSL bodies
+    pub fn check_unused_imports(&mut self, in_doc: &LiveOriginal) {
+        let tokens = &in_doc.tokens;
+        let is_ident = | index: usize | matches!(tokens.get(index), Some(t) if matches!(t.token, LiveToken::Ident(_)));
+        let is_token = | index: usize, token: LiveToken | matches!(tokens.get(index), Some(t) if t.token == token);
+        
+        let mut in_import = vec![false; tokens.len()];
+        for node in &in_doc.nodes {
+            if let LiveValue::Import(_) = &node.value {
+                // crate::module::path::(id | *) [as id]
+                let mut index = node.origin.token_id().unwrap().token_index();
+                in_import[index] = true;
+                index += 1;
+                while is_token(index, LiveToken::Punct(live_id!(::)))
+                    && (is_ident(index + 1) || is_token(index + 1, LiveToken::Punct(live_id!(*)))) {
+                    in_import[index] = true;
+                    in_import[index + 1] = true;
+                    index += 2;
+                }
+                if is_token(index, LiveToken::Ident(live_id!(as))) && is_ident(index + 1) {
+                    in_import[index] = true;
+                    in_import[index + 1] = true;
+                }
+            }
+        }
+        
+        let mut used = HashSet::new();
+        for (index, token) in tokens.iter().enumerate() {
+            if let LiveToken::Ident(id) = token.token {
+                if !in_import[index] {
+                    used.insert(id);
+                }
+            }
+        }
+        
+        for node in &in_doc.nodes {
+            if let LiveValue::Import(live_import) = &node.value {
+                let is_glob = node.id == LiveId::empty();
+                let is_used = if is_glob {
+                    // a glob import is only unused if none of the names it brings in are used
+                    if let Some(nodes) = self.live_registry.module_id_to_expanded_nodes(live_import.module_id) {
+                        let mut found = false;
+                        let mut node_iter = Some(1);
+                        while let Some(index) = node_iter {
+                            if used.contains(&nodes[index].id) {
+                                found = true;
+                                break;
+                            }
+                            node_iter = nodes.next_child(index);
+                        }
+                        found
+                    }
+                    else { // already reported as nothing found
+                        true
+                    }
+                }
+                else {
+                    used.contains(&node.id)
+                };
+                if !is_used {
+                    self.errors.push(LiveError {
+                        origin: live_error_origin!(),
+                        severity: LiveErrorSeverity::Warning,
+                        span: node.origin.token_id().unwrap().into(),
+                        message: if is_glob {
+                            format!("Unused import {}::*", live_import.module_id)
+                        }
+                        else {
+                            format!("Unused import {}::{} as {}", live_import.module_id, live_import.import_id, node.id)
+                        }
+                    });
+                }
+            }
+        }
+    }
+    
+    // optional authoring check on the expanded values, so malformed numbers
+    // get reported here instead of surfacing in the shader/render layer
+    pub fn validate_values(&mut self, out_doc: &LiveExpanded) {
+        for node in &out_doc.nodes {
+            // only report values defined in this file, inherited ones are checked in their own file
+            let token_id = if let Some(token_id) = node.origin.token_id() {token_id} else {continue};
+            if token_id.file_id() != Some(self.in_file_id) {
+                continue
+            }
+            let is_finite = match &node.value {
+                LiveValue::Float32(v) => v.is_finite(),
+                LiveValue::Float64(v) => v.is_finite(),
+                LiveValue::Vec2(v) => v.x.is_finite() && v.y.is_finite(),
+                LiveValue::Vec3(v) => v.x.is_finite() && v.y.is_finite() && v.z.is_finite(),
+                LiveValue::Vec4(v) => v.x.is_finite() && v.y.is_finite() && v.z.is_finite() && v.w.is_finite(),
+                _ => true
+            };
+            if !is_finite {
+                self.errors.push(LiveError {
+                    origin: live_error_origin!(),
+                    severity: LiveErrorSeverity::Warning,
+                    span: token_id.into(),
+                    message: format!("Value of {} is not a finite number {:?}", node.id, node.value)
+                });
+            }
+        }
+    }
 }
 
@@ -0,0 +1,93 @@
+use crate::{
+    makepad_live_id::*,
+    makepad_live_tokenizer::{FullToken, Delim, State, Cursor},
+};
+
+// canonical formatting for live source. The parser drops whitespace and comments,
+// so the formatter runs its own pass over the full token stream which keeps them as trivia.
+// Indentation is rebuilt from the delimiter depth, runs of spaces collapse to one,
+// a space just inside braces is kept when both braces end up on the same line,
+// at most one empty line is kept, and comments are copied through as written.
+pub fn format_live_source(source: &str) -> String {
+    let mut chars = Vec::new();
+    chars.extend(source.chars());
+    let mut state = State::default();
+    let mut scratch = String::new();
+    let mut cursor = Cursor::new(&chars, &mut scratch);
+    let mut last_index = 0usize;
+
+    let mut out = String::new();
+    let mut line = String::new();
+    let mut line_index = 0usize;
+    // for each open delimiter, the line a brace was opened on
+    let mut open_lines: Vec<Option<usize>> = Vec::new();
+    let mut pending_newlines = 0usize;
+    let mut pending_space = false;
+    let mut prev: Option<FullToken> = None;
+
+    loop {
+        let (next_state, full_token) = state.next(&mut cursor);
+        let full_token = if let Some(full_token) = full_token {full_token} else {break};
+        let text: String = chars[last_index..last_index + full_token.len].iter().collect();
+        state = next_state;
+        last_index = cursor.index();
+
+        if full_token.is_whitespace() {
+            let newlines = text.chars().filter( | c | *c == '\n').count();
+            if newlines > 0 {
+                pending_newlines += newlines;
+            }
+            else {
+                pending_space = true;
+            }
+            continue;
+        }
+
+        let open_line = if full_token.is_close() {open_lines.pop().flatten()} else {None};
+
+        if pending_newlines > 0 && (!line.is_empty() || !out.is_empty()) {
+            out.push_str(&line);
+            out.push('\n');
+            if pending_newlines > 1 {
+                out.push('\n');
+            }
+            line.clear();
+            line_index += if pending_newlines > 1 {2} else {1};
+        }
+        if line.is_empty() {
+            for _ in 0..open_lines.len() {
+                line.push_str("    ");
+            }
+        }
+        else if full_token.is_comment() || prev.as_ref().is_some_and( | prev | prev.is_comment()) {
+            if pending_space {
+                line.push(' ');
+            }
+        }
+        else {
+            let keep_brace_space = match &prev {
+                Some(prev) if prev.is_open_delim(Delim::Brace) => !full_token.is_close(),
+                _ => full_token.is_close_delim(Delim::Brace) && open_line == Some(line_index)
+            };
+            let no_space_after = !matches!(&prev, Some(prev) if !prev.is_open()) && !keep_brace_space;
+            let no_space_before = (full_token.is_close() && !keep_brace_space) || matches!(full_token.token, FullToken::Punct(live_id!(,)) | FullToken::Punct(live_id!(;)) | FullToken::Punct(live_id!(:)));
+            let force_space = matches!(prev, Some(FullToken::Punct(live_id!(,))) | Some(FullToken::Punct(live_id!(:))));
+            if (pending_space || force_space) && !no_space_after && !no_space_before {
+                line.push(' ');
+            }
+        }
+        line.push_str(&text);
+        pending_newlines = 0;
+        pending_space = false;
+
+        if full_token.is_open() {
+            open_lines.push(if full_token.is_open_delim(Delim::Brace) {Some(line_index)} else {None});
+        }
+        prev = Some(full_token.token);
+    }
+    if !line.trim().is_empty() {
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
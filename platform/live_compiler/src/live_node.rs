@@ -385,6 +385,7 @@ impl LivePropType {
     }
 }
 
+#[derive(Clone, Copy)]
 pub struct LiveEditInfo(u32);
 
 impl fmt::Debug for LiveEditInfo {
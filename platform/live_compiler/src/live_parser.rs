@@ -16,7 +16,7 @@ use {
         live_token::{LiveToken, TokenWithSpan, LiveTokenId},
         live_ptr::{LiveFileId, LiveModuleId, LivePtr},
         span::{TextSpan, TextPos},
-        live_error::{LiveError},
+        live_error::{LiveError, LiveErrorSeverity},
         live_document::LiveOriginal,
         live_node::{LiveDesignInfo, LiveDesignInfoIndex, LiveImport, LivePropType, LiveNode, LiveValue, LiveTypeInfo, LiveBinOp, LiveUnOp, LiveNodeOrigin, LiveEditInfo},
     }
@@ -32,6 +32,27 @@ pub struct LiveParser<'a> {
     pub end: TextPos,
 }
 
+type ParserState<'a> = (usize, usize, Cloned<Iter<'a, TokenWithSpan>>, TokenWithSpan);
+
+// a class body that is still open, with the id its close node gets
+struct ClassBody<'a> {
+    prop_id: LiveId,
+    nameless_id: u64,
+    after: AfterClassBody<'a>,
+}
+
+// what is left of the property whose value the class body is, once the body is closed
+enum AfterClassBody<'a> {
+    Nothing,
+    OptionalDelim,
+    Shorthand {
+        value_start: ParserState<'a>,
+        shorthand: Vec<(LiveTokenId, LiveId)>,
+        edit_info: Option<LiveEditInfo>,
+        assign_type: LivePropType,
+    },
+}
+
 impl<'a> LiveParser<'a> {
     pub fn new(tokens: &'a [TokenWithSpan], live_type_infos: &'a [LiveTypeInfo], file_id: LiveFileId) -> Self {
         let mut tokens_with_span = tokens.iter().cloned();
@@ -78,6 +99,7 @@ impl<'a> LiveParser<'a> {
     fn error(&mut self, message: String, origin: LiveErrorOrigin) -> LiveError {
         LiveError {
             origin,
+            severity: LiveErrorSeverity::Error,
             span: self.token_with_span.span.into(),
             message,
         }
@@ -456,6 +478,14 @@ impl<'a> LiveParser<'a> {
         Err(self.error("Eof in named enum".to_string(), live_error_origin!()))
     }
     
+    fn clone_state(&self) -> ParserState<'a> {
+        (self.token_index, self.live_type_info_counter, self.tokens_with_span.clone(), self.token_with_span.clone())
+    }
+    
+    fn restore_state(&mut self, state: ParserState<'a>) {
+        (self.token_index, self.live_type_info_counter, self.tokens_with_span, self.token_with_span) = state;
+    }
+    
     fn get_token_id(&self) -> LiveTokenId {
         LiveTokenId::new(self.file_id, self.token_index)
     }
@@ -516,6 +546,15 @@ impl<'a> LiveParser<'a> {
     }
     
     fn expect_live_value(&mut self, prop_id: LiveId, origin: LiveNodeOrigin, ld: &mut LiveOriginal) -> Result<(), LiveError> {
+        if self.begin_live_value(prop_id, origin, ld) ? {
+            self.expect_live_class(false, prop_id, ld) ?;
+        }
+        Ok(())
+    }
+    
+    // parses a value, except for the body of a class instance or object, which is left to the
+    // caller. returns true if such a body was opened
+    fn begin_live_value(&mut self, prop_id: LiveId, origin: LiveNodeOrigin, ld: &mut LiveOriginal) -> Result<bool, LiveError> {
         // now we can have an array or a class instance
         match self.peek_token() {
             LiveToken::Punct(live_id!(<))=>{ // class instance
@@ -530,7 +569,7 @@ impl<'a> LiveParser<'a> {
                     id: prop_id,
                     value: LiveValue::Clone{clone:ident, design_info}
                 });
-                self.expect_live_class(false, prop_id, ld) ?;
+                return Ok(true);
             }
             LiveToken::Ident(live_id!(struct)) => { // key/value map
                 self.skip_token();
@@ -540,7 +579,7 @@ impl<'a> LiveParser<'a> {
                     id: prop_id,
                     value: LiveValue::Clone{clone:live_id!(struct), design_info:LiveDesignInfoIndex::invalid()}
                 });
-                self.expect_live_class(false, prop_id, ld) ?;
+                return Ok(true);
             },
             
             LiveToken::Open(Delim::Brace) => { // key/value map
@@ -590,9 +629,7 @@ impl<'a> LiveParser<'a> {
                     }
                     
                     self.expect_token(LiveToken::Open(Delim::Brace)) ?;
-                    self.expect_live_class(false, prop_id, ld) ?;
-                    
-                    return Ok(());
+                    return Ok(true);
                 }
                 
                 ld.nodes.push(LiveNode {
@@ -600,7 +637,7 @@ impl<'a> LiveParser<'a> {
                     id: prop_id,
                     value: LiveValue::Object
                 });
-                self.expect_live_class(false, prop_id, ld) ?;
+                return Ok(true);
             },
             LiveToken::Open(Delim::Paren) => { // expression
                 self.expect_expression(prop_id, origin, ld) ?;
@@ -801,7 +838,7 @@ impl<'a> LiveParser<'a> {
             },
             other => return Err(self.error(format!("Unexpected token {} in property value", other), live_error_origin!()))
         }
-        Ok(())
+        Ok(false)
     }
     
     fn scan_to_token(&mut self, scan_token: LiveToken) -> Result<usize, LiveError> {
@@ -855,11 +892,18 @@ impl<'a> LiveParser<'a> {
     }
     
     fn expect_live_class(&mut self, root: bool, prop_id: LiveId, ld: &mut LiveOriginal) -> Result<(), LiveError> {
-        let mut nameless_id = 1;
+        // nested class bodies are kept on a stack instead of being parsed recursively, so deeply
+        // nested documents can't overflow the call stack
+        let mut bodies = vec![ClassBody {
+            prop_id,
+            nameless_id: 1,
+            after: AfterClassBody::Nothing
+        }];
         while self.peek_token() != LiveToken::Eof {
+            let prop_id = bodies.last().unwrap().prop_id;
             match self.peek_token() {
                 LiveToken::Close(Delim::Brace) => {
-                    if root {
+                    if root && bodies.len() == 1 {
                         return Err(self.error("Unexpected token } in root".to_string(), live_error_origin!()))
                     }
                     let token_id = self.get_token_id();
@@ -869,7 +913,16 @@ impl<'a> LiveParser<'a> {
                         id: prop_id,
                         value: LiveValue::Close
                     });
-                    return Ok(());
+                    match bodies.pop().unwrap().after {
+                        AfterClassBody::Nothing => (),
+                        AfterClassBody::OptionalDelim => self.accept_optional_delim(),
+                        AfterClassBody::Shorthand {value_start, shorthand, edit_info, assign_type} => {
+                            self.expect_shorthand(value_start, shorthand, edit_info, assign_type, ld) ?;
+                        }
+                    }
+                    if bodies.is_empty() {
+                        return Ok(());
+                    }
                 }
                 LiveToken::Punct(live_id!(<))=>{ // class instance
                     let token_id = self.get_token_id();
@@ -878,13 +931,18 @@ impl<'a> LiveParser<'a> {
                     let design_info = self.expect_design_info(ld)?;
                     self.expect_token(LiveToken::Punct(live_id!(>))) ?;
                     self.expect_token(LiveToken::Open(Delim::Brace))?;
+                    let body = bodies.last_mut().unwrap();
                     ld.nodes.push(LiveNode {
                         origin: LiveNodeOrigin::from_token_id(token_id).with_prop_type(LivePropType::Instance),
-                        id: LiveId(nameless_id),
+                        id: LiveId(body.nameless_id),
                         value: LiveValue::Clone{clone:ident, design_info}
                     });
-                    nameless_id += 1;
-                    self.expect_live_class(false, prop_id, ld) ?;
+                    body.nameless_id += 1;
+                    bodies.push(ClassBody {
+                        prop_id,
+                        nameless_id: 1,
+                        after: AfterClassBody::Nothing
+                    });
                 }
                 LiveToken::Ident(prop_id) => {
                     let token_id = self.get_token_id();
@@ -913,13 +971,27 @@ impl<'a> LiveParser<'a> {
                                     .with_node_has_prefix(true)
                                     .with_prop_type(prop_type);
                                 
-                                self.expect_live_value(real_prop_id, origin, ld) ?;
-                                //self.expect_node_with_prefix(ld) ?;
-                                self.accept_optional_delim();
+                                if self.begin_live_value(real_prop_id, origin, ld) ? {
+                                    bodies.push(ClassBody {
+                                        prop_id: real_prop_id,
+                                        nameless_id: 1,
+                                        after: AfterClassBody::OptionalDelim
+                                    });
+                                }
+                                else {
+                                    //self.expect_node_with_prefix(ld) ?;
+                                    self.accept_optional_delim();
+                                }
                             }
                         }
                     }
                     else { // has to be key:value
+                        // a, b, c = value is shorthand for a = value b = value c = value
+                        let mut shorthand = Vec::new();
+                        while self.accept_token(LiveToken::Punct(live_id!(,))) {
+                            let token_id = self.get_token_id();
+                            shorthand.push((token_id, self.expect_ident()?));
+                        }
                         // if we get a . metadata follows
                         let edit_info = self.possible_edit_info(ld) ?;
                   
@@ -927,20 +999,49 @@ impl<'a> LiveParser<'a> {
                         let origin = LiveNodeOrigin::from_token_id(token_id)
                             .with_edit_info(edit_info)
                             .with_prop_type(assign_type);
-                        self.expect_live_value(prop_id, origin, ld) ?;
-
-                        self.accept_optional_delim();
+                        let value_start = self.clone_state();
+                        if self.begin_live_value(prop_id, origin, ld) ? {
+                            bodies.push(ClassBody {
+                                prop_id,
+                                nameless_id: 1,
+                                after: AfterClassBody::Shorthand {value_start, shorthand, edit_info, assign_type}
+                            });
+                        }
+                        else {
+                            self.expect_shorthand(value_start, shorthand, edit_info, assign_type, ld) ?;
+                        }
                     }
                 },
                 other => return Err(self.error(format!("Unexpected token {} in class body of {}", other, prop_id), live_error_origin!()))
             }
         }
-        if root {
+        if root && bodies.len() == 1 {
             return Ok(())
         }
         Err(self.error("Eof in class body".to_string(), live_error_origin!()))
     }
     
+    // expands the shorthand by parsing the value again for every other id, so the expander only
+    // ever sees the long form
+    fn expect_shorthand(
+        &mut self,
+        value_start: ParserState<'a>,
+        shorthand: Vec<(LiveTokenId, LiveId)>,
+        edit_info: Option<LiveEditInfo>,
+        assign_type: LivePropType,
+        ld: &mut LiveOriginal
+    ) -> Result<(), LiveError> {
+        for (token_id, prop_id) in shorthand {
+            self.restore_state(value_start.clone());
+            let origin = LiveNodeOrigin::from_token_id(token_id)
+                .with_edit_info(edit_info)
+                .with_prop_type(assign_type);
+            self.expect_live_value(prop_id, origin, ld) ?;
+        }
+        self.accept_optional_delim();
+        Ok(())
+    }
+    
     pub fn expect_prop_type(&mut self)->Result<LivePropType, LiveError>{
         Ok(if self.accept_token(LiveToken::Punct(live_id!(:))){
             LivePropType::Field
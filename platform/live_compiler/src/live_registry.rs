@@ -5,7 +5,7 @@ use {
         makepad_live_id::*,
        // makepad_error_log::*,
         makepad_live_tokenizer::{TokenWithLen, Delim, FullToken, State, Cursor, live_error_origin, LiveErrorOrigin},
-        live_error::{LiveError, LiveErrorSpan, LiveFileError},
+        live_error::{LiveError, LiveErrorSeverity, LiveErrorSpan, LiveFileError},
         live_parser::LiveParser,
         live_document::{LiveOriginal, LiveExpanded},
         live_node::{LiveNodeOrigin, LiveNode, LiveValue, LiveType, LiveTypeInfo, LiveIdAsProp, LiveDesignInfo, LiveDesignInfoIndex},
@@ -15,6 +15,7 @@ use {
         live_token::{LiveToken, LiveTokenId, TokenWithSpan},
         span::{TextSpan, TextPos},
         live_expander::{LiveExpander},
+        live_formatter::format_live_source,
         live_component::{LiveComponentRegistries}
     }
 };
@@ -325,6 +326,20 @@ impl LiveRegistry {
         &self.live_files[token_id.file_id().unwrap().to_index()].expanded
     }
     
+    pub fn token_at_position(&self, file_id: LiveFileId, line: u32, column: u32) -> Option<LiveTokenId> {
+        let tokens = &self.live_files.get(file_id.to_index())?.original.tokens;
+        // the trailing Eof token has an empty span so leave it out of the search
+        let tokens = &tokens[0..tokens.len().saturating_sub(1)];
+        let pos = TextPos {line, column};
+        let index = tokens.partition_point( | token | token.span.start <= pos).checked_sub(1)?;
+        if pos < tokens[index].span.end {
+            Some(LiveTokenId::new(file_id, index))
+        }
+        else { // whitespace between tokens
+            None
+        }
+    }
+    
     pub fn module_id_to_file_id(&self, module_id: LiveModuleId) -> Option<LiveFileId> {
         self.module_id_to_file_id.get(&module_id).cloned()
     }
@@ -558,6 +573,7 @@ impl LiveRegistry {
                     FullToken::Unknown | FullToken::OtherNumber | FullToken::Lifetime => {
                         return Err(LiveError {
                             origin: live_error_origin!(),
+                            severity: LiveErrorSeverity::Error,
                             span: span.into(),
                             message: "Error tokenizing".to_string()
                         })
@@ -717,7 +733,7 @@ impl LiveRegistry {
         }
         if any_changes{
             // try to re-expand
-            self.expand_all_documents(errors);
+            self.expand_all_documents(errors, false);
         }
     }
 
@@ -821,7 +837,23 @@ impl LiveRegistry {
         Ok(file_id)
     }
     
-    pub fn expand_all_documents(&mut self, errors: &mut Vec<LiveError>) {
+    pub fn format_live_file(&self, file_id: LiveFileId) -> String {
+        format_live_source(&self.live_files[file_id.to_index()].source)
+    }
+    
+    pub fn reexpand_file(&mut self, file_id: LiveFileId) -> Vec<LiveError> {
+        // expand_all_documents picks up the dependents of a file marked for reexpansion
+        self.live_files[file_id.to_index()].reexpand = true;
+        let mut errors = Vec::new();
+        self.expand_all_documents(&mut errors, false);
+        errors.retain( | error | match &error.span {
+            LiveErrorSpan::Text(span) => span.file_id == file_id,
+            LiveErrorSpan::Token(span) => span.token_id.file_id() == Some(file_id),
+        });
+        errors
+    }
+    
+    pub fn expand_all_documents(&mut self, errors: &mut Vec<LiveError>, validate_values: bool) {
         // lets build up all dependencies here
         
         // alright so. we iterate
@@ -910,6 +942,9 @@ impl LiveRegistry {
                 errors
             };
             live_document_expander.expand(in_doc, &mut out_doc, self.live_files[file_id.to_index()].generation);
+            if validate_values {
+                live_document_expander.validate_values(&out_doc);
+            }
             
             self.live_files[file_id.to_index()].reexpand = false;
             std::mem::swap(&mut out_doc, &mut self.live_files[file_id.to_index()].expanded);
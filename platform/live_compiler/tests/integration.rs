@@ -1,4 +1,193 @@
+use makepad_live_compiler::{
+    format_live_source,
+    live_parser::LiveParser,
+    span::TextPos,
+    LiveDiagnostic, LiveErrorSeverity, LiveFileId, LiveId, LiveModuleId, LiveRegistry, LiveValue,
+};
+
+fn parse(source: &str) -> Vec<(LiveId, LiveValue)> {
+    let file_id = LiveFileId::new(0);
+    let tokens = LiveRegistry::tokenize_from_str(source, TextPos::default(), file_id).unwrap();
+    let mut parser = LiveParser::new(&tokens, &[], file_id);
+    let ld = parser.parse_live_document().unwrap();
+    ld.nodes.into_iter().map(|node| (node.id, node.value)).collect()
+}
+
 #[test]
 fn main() {
     // todo :)
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_shorthand_expands_to_long_form() {
+    assert_eq!(
+        parse("a, b, c = <Button> { text: \"x\", <Label> {} } d: 1"),
+        parse("a = <Button> { text: \"x\", <Label> {} } b = <Button> { text: \"x\", <Label> {} } c = <Button> { text: \"x\", <Label> {} } d: 1"),
+    );
+    assert_eq!(parse("a, b: [1, 2]"), parse("a: [1, 2] b: [1, 2]"));
+}
+
+#[test]
+fn test_validate_values_reports_non_finite_floats() {
+    let mut registry = LiveRegistry::default();
+    if let Err(why) = registry.register_live_file(
+        "test.rs",
+        "",
+        LiveModuleId::from_str("test").unwrap(),
+        "a: (0.0 / 0.0) b: 1.5".to_string(),
+        vec![],
+        TextPos::default(),
+    ) {
+        panic!("Couldnt parse file {}", why)
+    }
+    let mut errors = Vec::new();
+    registry.expand_all_documents(&mut errors, true);
+    assert_eq!(errors.len(), 1);
+    assert!(errors[0].message.contains("not a finite number"));
+    assert_eq!(errors[0].severity, LiveErrorSeverity::Warning);
+}
+
+#[test]
+fn test_token_at_position() {
+    let mut registry = LiveRegistry::default();
+    let file_id = match registry.register_live_file(
+        "test.rs",
+        "",
+        LiveModuleId::from_str("test").unwrap(),
+        "a: 1\nbcd: 2.5".to_string(),
+        vec![],
+        TextPos::default(),
+    ) {
+        Err(why) => panic!("Couldnt parse file {}", why),
+        Ok(file_id) => file_id
+    };
+    let token_text = | line, column | registry.token_at_position(file_id, line, column).map( | token_id | {
+        registry.token_id_to_token(token_id).token.to_string()
+    });
+    assert_eq!(token_text(0, 0).as_deref(), Some("a"));
+    assert_eq!(token_text(0, 2), None);
+    assert_eq!(token_text(1, 2).as_deref(), Some("bcd"));
+    assert_eq!(token_text(1, 3).as_deref(), Some(":"));
+    assert_eq!(token_text(1, 6).as_deref(), Some("2.5"));
+    assert_eq!(token_text(1, 8), None);
+    assert_eq!(token_text(5, 0), None);
+}
+
+#[test]
+fn test_error_to_diagnostic() {
+    let mut registry = LiveRegistry::default();
+    let file_id = match registry.register_live_file(
+        "test.rs",
+        "",
+        LiveModuleId::from_str("test").unwrap(),
+        "b: 1.5\nvalue: (0.0 / 0.0)".to_string(),
+        vec![],
+        TextPos::default(),
+    ) {
+        Err(why) => panic!("Couldnt parse file {}", why),
+        Ok(file_id) => file_id
+    };
+    let mut errors = Vec::new();
+    registry.expand_all_documents(&mut errors, true);
+    assert_eq!(errors.len(), 1);
+    let diagnostic = errors[0].to_diagnostic(&registry);
+    assert_eq!(diagnostic, LiveDiagnostic {
+        file_id,
+        start: (1, 0),
+        end: (1, 5),
+        message: errors[0].message.clone(),
+        severity: LiveErrorSeverity::Warning,
+    });
+}
+
+#[test]
+fn test_unused_imports_warn() {
+    let mut registry = LiveRegistry::default();
+    let files = [
+        ("theme.rs", "test::theme", "Foo: 1.0 Bar: 2.0"),
+        ("colors.rs", "test::colors", "Red: 1.0"),
+        ("app.rs", "test::app", "import crate::theme::Foo\nimport crate::theme::Bar\nimport crate::theme::*\nimport crate::colors::*\na: (Foo + 1.0)"),
+    ];
+    for (file_name, module_path, source) in files {
+        if let Err(why) = registry.register_live_file(
+            file_name,
+            "",
+            LiveModuleId::from_str(module_path).unwrap(),
+            source.to_string(),
+            vec![],
+            TextPos::default(),
+        ) {
+            panic!("Couldnt parse file {}", why)
+        }
+    }
+    let mut errors = Vec::new();
+    registry.expand_all_documents(&mut errors, false);
+    let warnings: Vec<_> = errors.iter()
+        .filter( | error | error.severity == LiveErrorSeverity::Warning)
+        .map( | error | registry.live_error_to_live_file_error(error.clone()).span.start.line)
+        .collect();
+    assert_eq!(warnings, vec![1, 3]);
+}
+
+#[test]
+fn test_reexpand_file_scopes_errors() {
+    let mut registry = LiveRegistry::default();
+    let mut file_ids = Vec::new();
+    let files = [
+        ("theme.rs", "test::theme", "Foo: 1.0"),
+        ("app.rs", "test::app", "import crate::theme::Missing\na: 1.0"),
+    ];
+    for (file_name, module_path, source) in files {
+        match registry.register_live_file(
+            file_name,
+            "",
+            LiveModuleId::from_str(module_path).unwrap(),
+            source.to_string(),
+            vec![],
+            TextPos::default(),
+        ) {
+            Err(why) => panic!("Couldnt parse file {}", why),
+            Ok(file_id) => file_ids.push(file_id)
+        }
+    }
+    let mut errors = Vec::new();
+    registry.expand_all_documents(&mut errors, false);
+    assert!(!errors.is_empty());
+    
+    // the app file is expanded again as a dependent, but its errors are not returned
+    assert!(registry.reexpand_file(file_ids[0]).is_empty());
+    assert_eq!(registry.reexpand_file(file_ids[1]).len(), errors.len());
+}
+
+#[test]
+fn test_format_live_source() {
+    let source = "\n// theme colors\nButton = <View>{\n  color:#f00,  // red\n\n\n      size: [1 ,2]\n  draw_bg: {\n fn pixel(self) -> vec4 { return vec4(1.0) }\n }\n}   \n";
+    let formatted = format_live_source(source);
+    assert_eq!(formatted, "// theme colors\nButton = <View>{\n    color: #f00, // red\n\n    size: [1, 2]\n    draw_bg: {\n        fn pixel(self) -> vec4 { return vec4(1.0) }\n    }\n}\n");
+    assert_eq!(format_live_source(&formatted), formatted);
+    assert_eq!(format_live_source("a = { }\nb = {c: 1}\nd = {  e: 1\n}\n"), "a = {}\nb = {c: 1}\nd = { e: 1\n}\n");
+}
+
+#[test]
+fn test_deep_nesting() {
+    let depth = 5000;
+    let source = format!("{}x: 1{}", "a = { ".repeat(depth), " }".repeat(depth));
+    let nodes = parse(&source);
+    assert_eq!(nodes.len(), 2 * depth + 3);
+    assert_eq!(nodes[depth + 1], (LiveId::from_str("x"), LiveValue::Int64(1)));
+
+    let mut registry = LiveRegistry::default();
+    if let Err(why) = registry.register_live_file(
+        "test.rs",
+        "",
+        LiveModuleId::from_str("test").unwrap(),
+        source,
+        vec![],
+        TextPos::default(),
+    ) {
+        panic!("Couldnt parse file {}", why)
+    }
+    let mut errors = Vec::new();
+    registry.expand_all_documents(&mut errors, true);
+    assert!(errors.is_empty());
+}
@@ -11,6 +11,7 @@ use{
             },
             LiveRegistry,
             LiveError,
+            LiveErrorSeverity,
             live_error_origin,
             LiveErrorOrigin,
             TokenSpan
@@ -326,6 +327,7 @@ impl<'a> DrawShaderAnalyser<'a> {
                     _ => {
                         return Err(LiveError {
                             origin: live_error_origin!(),
+                            severity: LiveErrorSeverity::Error,
                             span: decl.span.into(),
                             message: String::from(
                                 "attribute must be either a floating-point scalar or vector or mat4",
@@ -342,6 +344,7 @@ impl<'a> DrawShaderAnalyser<'a> {
                     _ => {
                         return Err(LiveError {
                             origin: live_error_origin!(),
+                            severity: LiveErrorSeverity::Error,
                             span: decl.span.into(),
                             message: String::from(
                                 "attribute must be either a floating-point scalar or vector or mat4",
@@ -358,6 +361,7 @@ impl<'a> DrawShaderAnalyser<'a> {
                     _ => {
                         return Err(LiveError {
                             origin: live_error_origin!(),
+                            severity: LiveErrorSeverity::Error,
                             span: decl.span.into(),
                             message: String::from("texture must be either a texture2D or a textureOES (android)"),
                         })
@@ -376,6 +380,7 @@ impl<'a> DrawShaderAnalyser<'a> {
                     _ => {
                         return Err(LiveError {
                             origin: live_error_origin!(),
+                            severity: LiveErrorSeverity::Error,
                             span: decl.span.into(),
                             message: String::from(
                                 "varying must be either a floating-point scalar or vector",
@@ -407,6 +412,7 @@ impl<'a> DrawShaderAnalyser<'a> {
                 _ => {
                     return Err(LiveError {
                         origin: live_error_origin!(),
+                        severity: LiveErrorSeverity::Error,
                         span: def.span.into(),
                         message: String::from(
                             "function `vertex` must return a value of type `vec4`",
@@ -420,6 +426,7 @@ impl<'a> DrawShaderAnalyser<'a> {
                 _ => {
                     return Err(LiveError {
                         origin: live_error_origin!(),
+                        severity: LiveErrorSeverity::Error,
                         span: def.span.into(),
                         message: String::from(
                             "function `fragment` must return a value of type `vec4`",
@@ -432,6 +439,7 @@ impl<'a> DrawShaderAnalyser<'a> {
                 Ty::Array {..} => {
                     return Err(LiveError {
                         origin: live_error_origin!(),
+                        severity: LiveErrorSeverity::Error,
                         span: def.span.into(),
                         message: String::from("functions can't return arrays"),
                     })
@@ -471,6 +479,7 @@ impl<'a> DrawShaderAnalyser<'a> {
             if call_stack.contains(&sub_ptr) {
                 return Err(LiveError {
                     origin: live_error_origin!(),
+                    severity: LiveErrorSeverity::Error,
                     span: sub_decl.span.into(),
                     message: format!("Struct has recursively dependency"),
                 });
@@ -509,6 +518,7 @@ impl<'a> DrawShaderAnalyser<'a> {
             if call_stack.contains(&callee_decl.fn_ptr) {
                 return Err(LiveError {
                     origin: live_error_origin!(),
+                    severity: LiveErrorSeverity::Error,
                     span: def.span.into(),
                     message: format!("function `{}` recursively calls `{}`", def.ident, callee_decl.ident),
                 });
@@ -555,6 +565,7 @@ impl<'a> ConstAnalyser<'a> {
         if expected_ty != actual_ty {
             return Err(LiveError {
                 origin: live_error_origin!(),
+                severity: LiveErrorSeverity::Error,
                 span: self.const_def.span.into(),
                 message: String::from("Declared type and inferred type not the same"),
             } .into());
@@ -672,6 +683,7 @@ impl<'a> FnDefAnalyser<'a> {
                 if !self.fn_def.has_return.get() {
                     return Err(LiveError {
                         origin: live_error_origin!(),
+                        severity: LiveErrorSeverity::Error,
                         span: self.fn_def.span.into(),
                         message: format!(
                             "Function has no return",
@@ -712,6 +724,7 @@ impl<'a> FnDefAnalyser<'a> {
                     if closure_def.params.len() != params.len() {
                         return Err(LiveError {
                             origin: live_error_origin!(),
+                            severity: LiveErrorSeverity::Error,
                             span: closure_def.span.into(),
                             message: format!(
                                 "Closure does not have the same number of arguments as function decl: {} expected: {}",
@@ -742,6 +755,7 @@ impl<'a> FnDefAnalyser<'a> {
                             if expr.ty.borrow().as_ref() != return_ty.borrow().as_ref() {
                                 return Err(LiveError {
                                     origin: live_error_origin!(),
+                                    severity: LiveErrorSeverity::Error,
                                     span: closure_def.span.into(),
                                     message: format!(
                                         "Closure return type not correct: {} expected: {}",
@@ -790,6 +804,7 @@ impl<'a> FnDefAnalyser<'a> {
         if self.scopes.closure_sites.borrow().len()>0 {
             return Err(LiveError {
                 origin: live_error_origin!(),
+                severity: LiveErrorSeverity::Error,
                 span: self.fn_def.span.into(),
                 message: format!("Nesting closures is not supported at the moment"),
             });
@@ -846,6 +861,7 @@ impl<'a> FnDefAnalyser<'a> {
         if !self.is_inside_loop {
             return Err(LiveError {
                 origin: live_error_origin!(),
+                severity: LiveErrorSeverity::Error,
                 span:span.into(),
                 message: String::from("break outside loop"),
             } .into());
@@ -857,6 +873,7 @@ impl<'a> FnDefAnalyser<'a> {
         if !self.is_inside_loop {
             return Err(LiveError {
                 origin: live_error_origin!(),
+                severity: LiveErrorSeverity::Error,
                 span:span.into(),
                 message: String::from("continue outside loop"),
             } .into());
@@ -905,6 +922,7 @@ impl<'a> FnDefAnalyser<'a> {
             if step == 0 {
                 return Err(LiveError {
                     origin: live_error_origin!(),
+                    severity: LiveErrorSeverity::Error,
                     span:span.into(),
                     message: String::from("step must not be zero"),
                 } .into());
@@ -912,6 +930,7 @@ impl<'a> FnDefAnalyser<'a> {
             if from < to && step < 0 {
                 return Err(LiveError {
                     origin: live_error_origin!(),
+                    severity: LiveErrorSeverity::Error,
                     span:span.into(),
                     message: String::from("step must not be positive"),
                 } .into());
@@ -919,6 +938,7 @@ impl<'a> FnDefAnalyser<'a> {
             if from > to && step > 0 {
                 return Err(LiveError {
                     origin: live_error_origin!(),
+                    severity: LiveErrorSeverity::Error,
                     span:span.into(),
                     message: String::from("step must not be negative"),
                 } .into());
@@ -984,6 +1004,7 @@ impl<'a> FnDefAnalyser<'a> {
                 if match_item.enum_name.0 != shader_enum.enum_name{
                     return Err(LiveError {
                         origin: live_error_origin!(),
+                        severity: LiveErrorSeverity::Error,
                         span:span.into(),
                         message: format!("Enum name mismatched, expected {} got {}", shader_enum.enum_name, match_item.enum_name.0),
                     } .into())
@@ -995,6 +1016,7 @@ impl<'a> FnDefAnalyser<'a> {
                 else{
                     return Err(LiveError {
                         origin: live_error_origin!(),
+                        severity: LiveErrorSeverity::Error,
                         span:span.into(),
                         message: format!("Variant not found on enum {}::{}", match_item.enum_name.0, match_item.enum_variant.0),
                     } .into())
@@ -1010,6 +1032,7 @@ impl<'a> FnDefAnalyser<'a> {
         else {
             Err(LiveError {
                 origin: live_error_origin!(),
+                severity: LiveErrorSeverity::Error,
                 span:span.into(),
                 message: String::from("Can only match on enum types"),
             } .into())
@@ -1029,6 +1052,7 @@ impl<'a> FnDefAnalyser<'a> {
             if expr.is_none() {
                 return Err(LiveError {
                     origin: live_error_origin!(),
+                    severity: LiveErrorSeverity::Error,
                     span:span.into(),
                     message: format!("cannot define an uninitialised variable `{}`", ident),
                 });
@@ -1049,6 +1073,7 @@ impl<'a> FnDefAnalyser<'a> {
             if ty == Ty::Void {
                 return Err(LiveError {
                     origin: live_error_origin!(),
+                    severity: LiveErrorSeverity::Error,
                     span:span.into(),
                     message: String::from("init expression cannot be void")
                 });
@@ -1060,6 +1085,7 @@ impl<'a> FnDefAnalyser<'a> {
         } else {
             return Err(LiveError {
                 origin: live_error_origin!(),
+                severity: LiveErrorSeverity::Error,
                 span:span.into(),
                 message: format!("can't infer type of variable `{}`", ident),
             });
@@ -1099,6 +1125,7 @@ impl<'a> FnDefAnalyser<'a> {
         } else if self.fn_def.return_ty.borrow().as_ref().unwrap() != &Ty::Void {
             return Err(LiveError {
                 origin: live_error_origin!(),
+                severity: LiveErrorSeverity::Error,
                 span:span.into(),
                 message: String::from("missing return expression"),
             } .into());
@@ -3,6 +3,7 @@ use{
     crate::{
         makepad_live_compiler::{
             LiveError,
+            LiveErrorSeverity,
             LiveErrorOrigin,
             live_error_origin,
             TokenSpan
@@ -20,6 +21,7 @@ impl ConstEvaluator {
     pub fn const_eval_expr(&self, expr: &Expr) -> Result<Val, LiveError> {
         self.try_const_eval_expr(expr).ok_or_else(|| LiveError {
             origin:live_error_origin!(),
+            severity: LiveErrorSeverity::Error,
             span: expr.span.into(),
             message: String::from("expression is not const"),
         })
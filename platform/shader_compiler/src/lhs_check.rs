@@ -80,6 +80,7 @@ impl<'a> LhsChecker<'a> {
     ) -> Result<(), LiveError> {
         return Err(LiveError {
             origin: live_error_origin!(),
+            severity: LiveErrorSeverity::Error,
             span:span.into(),
             message: String::from("expression is not a valid left hand side"),
         });
@@ -94,6 +95,7 @@ impl<'a> LhsChecker<'a> {
     ) -> Result<(), LiveError> {
         return Err(LiveError {
             origin: live_error_origin!(),
+            severity: LiveErrorSeverity::Error,
             span:span.into(),
             message: String::from("expression is not a valid left hand side"),
         });
@@ -108,6 +110,7 @@ impl<'a> LhsChecker<'a> {
     ) -> Result<(), LiveError> {
         return Err(LiveError {
             origin:live_error_origin!(),
+            severity: LiveErrorSeverity::Error,
             span:span.into(),
             message: String::from("expression is not a valid left hand side"),
         });
@@ -116,6 +119,7 @@ impl<'a> LhsChecker<'a> {
     fn lhs_check_un_expr(&mut self, span: TokenSpan, _op: UnOp, _expr: &Expr) -> Result<(), LiveError> {
         return Err(LiveError {
             origin:live_error_origin!(),
+            severity: LiveErrorSeverity::Error,
             span:span.into(),
             message: String::from("expression is not a valid left hand side"),
         });
@@ -127,6 +131,7 @@ impl<'a> LhsChecker<'a> {
     ) -> Result<(), LiveError> {
         return Err(LiveError {
             origin:live_error_origin!(),
+            severity: LiveErrorSeverity::Error,
             span:span.into(),
             message: String::from("expression is not a valid left hand side"),
         });
@@ -149,6 +154,7 @@ impl<'a> LhsChecker<'a> {
                     _=>{
                         Err(LiveError {
                             origin:live_error_origin!(),
+                            severity: LiveErrorSeverity::Error,
                             span:span.into(),
                             message: String::from("Can only assign to varying values for shader self"),
                         })
@@ -178,6 +184,7 @@ impl<'a> LhsChecker<'a> {
     ) -> Result<(), LiveError> {
         return Err(LiveError {
             origin:live_error_origin!(),
+            severity: LiveErrorSeverity::Error,
             span:span.into(),
             message: String::from("expression is not a valid left hand side"),
         });
@@ -194,6 +201,7 @@ impl<'a> LhsChecker<'a> {
         else{
             Err(LiveError {
                 origin:live_error_origin!(),
+                severity: LiveErrorSeverity::Error,
                 span:span.into(),
                 message: String::from("expression is not a valid left hand side"),
             })
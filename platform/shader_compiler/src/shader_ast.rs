@@ -1125,6 +1125,7 @@ impl Ty {
                 _ => {
                     return Err(LiveError {
                         origin: live_error_origin!(),
+                        severity: LiveErrorSeverity::Error,
                         message: format!("Id does not resolve to a shader type {}", id),
                         span: origin.token_id().unwrap().into()
                     })
@@ -1140,6 +1141,7 @@ impl Ty {
             LiveValue::Vec4(_) => Self::Vec4,
             _ => return Err(LiveError {
                 origin: live_error_origin!(),
+                severity: LiveErrorSeverity::Error,
                 message: format!("Live value {:?} does not resolve to a shader type", value),
                 span: origin.token_id().unwrap().into()
             })
@@ -104,6 +104,7 @@ impl<'a> ShaderParser<'a> {
     fn error(&mut self, origin: LiveErrorOrigin, message: String) -> LiveError {
         LiveError {
             origin,
+            severity: LiveErrorSeverity::Error,
             span: self.token_with_span.span.into(),
             message,
         }
@@ -1431,6 +1432,7 @@ impl SpanTracker {
     pub fn error(&self, parser: &mut ShaderParser, origin: LiveErrorOrigin, message: String) -> LiveError {
         LiveError {
             origin,
+            severity: LiveErrorSeverity::Error,
             span: TextSpan {
                 file_id: self.file_id,
                 start: self.start,
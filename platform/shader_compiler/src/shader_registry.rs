@@ -207,6 +207,7 @@ impl ShaderRegistry {
                     return LiveNodeFindResult::Error(
                         LiveError {
                             origin: live_error_origin!(),
+                            severity: LiveErrorSeverity::Error,
                             message: format!("Type of eval result not valid for shader"),
                             span: nodes[index].origin.token_id().unwrap().into()
                         }
@@ -420,6 +421,7 @@ impl ShaderRegistry {
                                 _ => {
                                     return Err(LiveError {
                                         origin: live_error_origin!(),
+                                        severity: LiveErrorSeverity::Error,
                                         span: prop.origin.token_id().unwrap().into(),
                                         message: format!("Unexpected DSL node")
                                     })
@@ -446,6 +448,7 @@ impl ShaderRegistry {
                                 // TODO support structs as fields here
                                 return Err(LiveError {
                                     origin: live_error_origin!(),
+                                    severity: LiveErrorSeverity::Error,
                                     span: prop.origin.token_id().unwrap().into(),
                                     message: format!("Type not found for struct field {}", type_name)
                                 })
@@ -454,6 +457,7 @@ impl ShaderRegistry {
                         _ => {
                             return Err(LiveError {
                                 origin: live_error_origin!(),
+                                severity: LiveErrorSeverity::Error,
                                 span: prop.origin.token_id().unwrap().into(),
                                 message: format!("Cannot use {:?} in struct", prop.value)
                             })
@@ -548,6 +552,7 @@ impl ShaderRegistry {
                                 }*/
                                 return Err(LiveError {
                                     origin: live_error_origin!(),
+                                    severity: LiveErrorSeverity::Error,
                                     span: prop.origin.token_id().unwrap().into(),
                                     message: format!("Can only support field colon : values don't use =")
                                 })
@@ -658,6 +663,7 @@ impl ShaderRegistry {
                                 _ => {
                                     return Err(LiveError {
                                         origin: live_error_origin!(),
+                                        severity: LiveErrorSeverity::Error,
                                         span: first_def.into(),
                                         message: format!("Unexpected variable prefix {:?}", before)
                                     })
@@ -707,6 +713,7 @@ impl ShaderRegistry {
                                 _ => {
                                     return Err(LiveError {
                                         origin: live_error_origin!(),
+                                        severity: LiveErrorSeverity::Error,
                                         span: token.span.into(),
                                         message: format!("Unexpected in shader body {}", token)
                                     })
@@ -756,6 +763,7 @@ impl ShaderRegistry {
                             
                             return Err(LiveError {
                                 origin: live_error_origin!(),
+                                severity: LiveErrorSeverity::Error,
                                 span: field_a.span.into(),
                                 message: format!("Field double declaration  {}",field_a.ident)
                             })
@@ -768,6 +776,7 @@ impl ShaderRegistry {
                 if !method_set.contains(&live_id!(vertex)) {
                     return Err(LiveError {
                         origin: live_error_origin!(),
+                        severity: LiveErrorSeverity::Error,
                         span: class_node.origin.token_id().unwrap().into(),
                         message: format!("analyse_draw_shader missing vertex method")
                     })
@@ -776,6 +785,7 @@ impl ShaderRegistry {
                 if !method_set.contains(&live_id!(pixel)) {
                     return Err(LiveError {
                         origin: live_error_origin!(),
+                        severity: LiveErrorSeverity::Error,
                         span: class_node.origin.token_id().unwrap().into(),
                         message: format!("analyse_draw_shader missing pixel method")
                     })
@@ -800,6 +810,7 @@ impl ShaderRegistry {
             }
             x => return Err(LiveError {
                 origin: live_error_origin!(),
+                severity: LiveErrorSeverity::Error,
                 span: class_node.origin.token_id().unwrap().into(),
                 message: format!("analyse_draw_shader could not find shader class {:?}", x)
             })
@@ -82,6 +82,7 @@ impl<'a> TyChecker<'a> {
         if &actual_ty != expected_ty {
             return Err(LiveError {
                 origin: live_error_origin!(),
+                severity: LiveErrorSeverity::Error,
                 span:span.into(),
                 message: format!(
                     "can't match expected type `{}` with actual type `{}",
@@ -354,6 +355,7 @@ impl<'a> TyChecker<'a> {
         }
         .ok_or_else( || LiveError {
             origin: live_error_origin!(),
+            severity: LiveErrorSeverity::Error,
             span:span.into(),
             message: format!(
                 "can't apply binary operator `{}` to operands of type `{}` and `{}",
@@ -383,6 +385,7 @@ impl<'a> TyChecker<'a> {
         }
         .ok_or_else( || LiveError {
             origin: live_error_origin!(),
+            severity: LiveErrorSeverity::Error,
             span:span.into(),
             message: format!(
                 "can't apply unary operator `{}` to operand of type `{}`",
@@ -413,6 +416,7 @@ impl<'a> TyChecker<'a> {
                     if closure_args.len() > 0{
                         return Err(LiveError {
                             origin: live_error_origin!(),
+                            severity: LiveErrorSeverity::Error,
                             span,
                             message: format!("Cannot pass closures to closures, please implement"),
                         })
@@ -426,6 +430,7 @@ impl<'a> TyChecker<'a> {
         }
         Err(LiveError {
             origin: live_error_origin!(),
+            severity: LiveErrorSeverity::Error,
             span,
             message: format!("Closure call `{}` is not defined on", ident),
         })
@@ -455,6 +460,7 @@ impl<'a> TyChecker<'a> {
                         if closure_args.len() > 0{
                             return Err(LiveError {
                                 origin: live_error_origin!(),
+                                severity: LiveErrorSeverity::Error,
                                 span:span.into(),
                                 message: format!("Cannot pass closures to closures, please implement"),
                             })
@@ -479,6 +485,7 @@ impl<'a> TyChecker<'a> {
         }
         return Err(LiveError {
             origin: live_error_origin!(),
+            severity: LiveErrorSeverity::Error,
             span:span.into(),
             message: format!("Function not found {}", ident.unwrap()),
         }) 
@@ -512,6 +519,7 @@ impl<'a> TyChecker<'a> {
                     }
                     return Err(LiveError {
                         origin: live_error_origin!(),
+                        severity: LiveErrorSeverity::Error,
                         span:span.into(),
                         message: format!("shader method `{}` is not type checked `{}`", ident, ty),
                     });
@@ -535,6 +543,7 @@ impl<'a> TyChecker<'a> {
                     }
                     return Err(LiveError {
                         origin: live_error_origin!(),
+                        severity: LiveErrorSeverity::Error,
                         span:span.into(),
                         message: format!("struct method `{}` is not type checked `{}`", ident, ty),
                     });
@@ -544,6 +553,7 @@ impl<'a> TyChecker<'a> {
         }
         Err(LiveError {
             origin: live_error_origin!(),
+            severity: LiveErrorSeverity::Error,
             span:span.into(),
             message: format!("method `{}` is not defined on type `{}`", ident, ty),
         })
@@ -583,7 +593,7 @@ impl<'a> TyChecker<'a> {
                 write!(message, "{}{}", sep, arg_ty).unwrap();
                 sep = ", ";
             }
-            LiveError {origin: live_error_origin!(), span:span.into(), message}
+            LiveError {origin: live_error_origin!(), severity: LiveErrorSeverity::Error, span:span.into(), message}
         }) ? .clone())
     }
     
@@ -598,6 +608,7 @@ impl<'a> TyChecker<'a> {
         match self.check_params_against_args(span, &fn_def.params, arg_exprs) {
            Err(err)=> Err(LiveError {
                 origin: live_error_origin!(),
+                severity: LiveErrorSeverity::Error,
                 span:span.into(),
                 message: format!("function: `{}`: {}", self.shader_registry.fn_ident_from_ptr(self.live_registry, fn_ptr), err.message)
             }),
@@ -607,6 +618,7 @@ impl<'a> TyChecker<'a> {
                     if closure_site_index.is_none(){
                         return Err(LiveError {
                             origin: live_error_origin!(),
+                            severity: LiveErrorSeverity::Error,
                             span:span.into(),
                             message: format!("Closures not supported here {}", self.shader_registry.fn_ident_from_ptr(self.live_registry, fn_ptr))
                         });
@@ -632,6 +644,7 @@ impl<'a> TyChecker<'a> {
         if arg_exprs.len() < params.len() {
             return Err(LiveError {
                 origin: live_error_origin!(),
+                severity: LiveErrorSeverity::Error,
                 span:span.into(),
                 message: format!(
                     "not enough arguments expected {}, got {}",
@@ -644,6 +657,7 @@ impl<'a> TyChecker<'a> {
         if arg_exprs.len() > params.len() {
             return Err(LiveError {
                 origin: live_error_origin!(),
+                severity: LiveErrorSeverity::Error,
                 span:span.into(),
                 message: format!(
                     "too many arguments for call expected {}, got {}",
@@ -676,6 +690,7 @@ impl<'a> TyChecker<'a> {
             if arg_ty != param_ty {
                 return Err(LiveError {
                     origin: live_error_origin!(),
+                    severity: LiveErrorSeverity::Error,
                     span:span.into(),
                     message: format!(
                         "wrong type for argument {} expected `{}`, got `{}`",
@@ -719,6 +734,7 @@ impl<'a> TyChecker<'a> {
                 })
                     .ok_or_else( || LiveError {
                     origin: live_error_origin!(),
+                    severity: LiveErrorSeverity::Error,
                     span:span.into(),
                     message: format!("field `{}` is not defined on type `{}`", field_ident, ty),
                 }) ?;
@@ -750,6 +766,7 @@ impl<'a> TyChecker<'a> {
             Ty::Struct(struct_ptr) => {
                 Ok(self.shader_registry.structs.get(&struct_ptr) .unwrap() .find_field(field_ident) .ok_or(LiveError {
                     origin: live_error_origin!(),
+                    severity: LiveErrorSeverity::Error,
                     span:span.into(),
                     message: format!("field `{}` is not defined on type `{:?}`", field_ident, struct_ptr),
                 }) ? .ty_expr .ty .borrow() .as_ref() .unwrap() .clone())
@@ -757,12 +774,14 @@ impl<'a> TyChecker<'a> {
             Ty::DrawShader(shader_ptr) => {
                 Ok(self.shader_registry.draw_shader_defs.get(&shader_ptr).unwrap().find_field(field_ident) .ok_or(LiveError {
                     origin: live_error_origin!(),
+                    severity: LiveErrorSeverity::Error,
                     span:span.into(),
                     message: format!("field `{}` is not defined on shader `{:?}`", field_ident, shader_ptr),
                 }) ? .ty_expr .ty .borrow() .as_ref() .unwrap() .clone())
             }
             _ => Err(LiveError {
                 origin: live_error_origin!(),
+                severity: LiveErrorSeverity::Error,
                 span:span.into(),
                 message: format!("can't access field on value of type `{}`", ty).into(),
             }),
@@ -787,6 +806,7 @@ impl<'a> TyChecker<'a> {
             _ => {
                 return Err(LiveError {
                     origin: live_error_origin!(),
+                    severity: LiveErrorSeverity::Error,
                     span:span.into(),
                     message: format!("can't index into value of type `{}`", ty).into(),
                 })
@@ -795,6 +815,7 @@ impl<'a> TyChecker<'a> {
         if index_ty != Ty::Int {
             return Err(LiveError {
                 origin: live_error_origin!(),
+                severity: LiveErrorSeverity::Error,
                 span:span.into(),
                 message: "index is not an integer".into(),
             });
@@ -839,6 +860,7 @@ impl<'a> TyChecker<'a> {
                 if actual_slots < expected_slots {
                     return Err(LiveError {
                         origin: live_error_origin!(),
+                        severity: LiveErrorSeverity::Error,
                         span:span.into(),
                         message: format!(
                             "not enough components for call to constructor `{}`: expected {}, got {}",
@@ -852,6 +874,7 @@ impl<'a> TyChecker<'a> {
                 if actual_slots > expected_slots {
                     return Err(LiveError {
                         origin: live_error_origin!(),
+                        severity: LiveErrorSeverity::Error,
                         span:span.into(),
                         message: format!(
                             "too many components for call to constructor `{}`: expected {}, got {}",
@@ -866,6 +889,7 @@ impl<'a> TyChecker<'a> {
             }
             _ => Err(LiveError {
                 origin: live_error_origin!(),
+                severity: LiveErrorSeverity::Error,
                 span:span.into(),
                 message: format!(
                     "can't construct value of type `{}` with arguments of types `{}`",
@@ -904,6 +928,7 @@ impl<'a> TyChecker<'a> {
                             
                             return Err(LiveError {
                                 origin: live_error_origin!(),
+                                severity: LiveErrorSeverity::Error,
                                 span:span.into(),
                                 message: format!("`{}` is a closure and cannot be used as a variable", ident),
                             })
@@ -922,6 +947,7 @@ impl<'a> TyChecker<'a> {
             VarResolve::Function(fn_ptr) => {
                 return Err(LiveError {
                     origin: live_error_origin!(),
+                    severity: LiveErrorSeverity::Error,
                     span:span.into(),
                     message: format!("`{}` implement using functions as closure args", ident.unwrap()),
                 })
@@ -930,6 +956,7 @@ impl<'a> TyChecker<'a> {
                  
                 return Err(LiveError {
                     origin: live_error_origin!(),
+                    severity: LiveErrorSeverity::Error,
                     span:span.into(),
                     message: format!("`{}` is not defined in this scope", ident.unwrap()),
                 })
@@ -955,6 +982,7 @@ impl<'a> TyChecker<'a> {
                 if field_ty.as_ref() != my_ty.as_ref() {
                     return Err(LiveError {
                         origin: live_error_origin!(),
+                        severity: LiveErrorSeverity::Error,
                         span:span.into(),
                         message: format!("field `{}` is the wrong type {} instead of {}", ident, my_ty.as_ref().unwrap(), field_ty.as_ref().unwrap()),
                     })
@@ -963,6 +991,7 @@ impl<'a> TyChecker<'a> {
             else {
                 return Err(LiveError {
                     origin: live_error_origin!(),
+                    severity: LiveErrorSeverity::Error,
                     span:span.into(),
                     message: format!("`{}` is not a valid struct field", ident),
                 })
@@ -973,6 +1002,7 @@ impl<'a> TyChecker<'a> {
             if args.iter().position( | (ident, expr) | ident == &field.ident).is_none() {
                 return Err(LiveError {
                     origin: live_error_origin!(),
+                    severity: LiveErrorSeverity::Error,
                     span:span.into(),
                     message: format!("`{}` field is missing", field.ident),
                 })
@@ -983,6 +1013,7 @@ impl<'a> TyChecker<'a> {
                 if args[i].0 == args[j].0 { // duplicate
                     return Err(LiveError {
                         origin: live_error_origin!(),
+                        severity: LiveErrorSeverity::Error,
                         span:span.into(),
                         message: format!("`{}` field is duplicated", args[i].0),
                     })
@@ -381,7 +381,7 @@ fn main() {
     }
     
     let mut errors = Vec::new();
-    sr.live_registry.expand_all_documents(&mut errors);
+    sr.live_registry.expand_all_documents(&mut errors, false);
     
     for msg in errors {
         println!("{}\n", msg.to_live_file_error("", SOURCE));
@@ -280,7 +280,11 @@ impl DrawVars {
                     cx.draw_shaders.error_fingerprints.push(fingerprint);
                     // ok so. lets get the source for this file id
                     let err = live_registry.live_error_to_live_file_error(e);
-                    if std::env::args().find(|v| v == "--message-format=json").is_some(){
+                    if cx.in_makepad_studio {
+                        // studio logs and decorates the error itself
+                        Cx::stdin_send_error(&err);
+                    }
+                    else if std::env::args().find(|v| v == "--message-format=json").is_some(){
                         crate::log::log_with_level(
                             &err.file,
                             err.span.start.line,
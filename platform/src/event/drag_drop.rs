@@ -4,6 +4,7 @@ use {
     crate::{
         makepad_live_id::*,
         makepad_math::*,
+        makepad_micro_serde::*,
         event::{
             KeyModifiers,
             finger::{HitOptions, Margin},
@@ -65,7 +66,7 @@ pub enum DragResponse {
     Move,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, SerBin, DeBin, SerJson, DeJson)]
 pub enum DragItem {
     FilePath {path: String, internal_id: Option<LiveId>},
     String {value: String, internal_id: Option<LiveId>}
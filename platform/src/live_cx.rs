@@ -10,6 +10,7 @@ use {
             LiveId,
             LiveProp,
             LiveError,
+            LiveErrorSeverity,
             LiveModuleId,
            /*LiveToken,*/
             LivePtr,
@@ -159,6 +160,7 @@ impl Cx {
         if let Some(token_id) = &nodes[index].origin.token_id() {
             let err = LiveError {
                 origin,
+                severity: LiveErrorSeverity::Error,
                 message,
                 span: (*token_id).into()
             };
@@ -267,7 +269,12 @@ impl Cx {
             let mut errs = Vec::new();
             live_registry.process_file_changes(all_changes, &mut errs);
             for err in errs {
-                
+                let is_warning = err.severity == LiveErrorSeverity::Warning;
+                if self.in_makepad_studio && !is_warning {
+                    // studio logs and decorates the error itself, so don't log it here as well
+                    Cx::stdin_send_error(&live_registry.live_error_to_live_file_error(err));
+                    continue
+                }
                 // alright we need to output the correct error
                 if std::env::args().find(|v| v == "--message-format=json").is_some(){
                     let err = live_registry.live_error_to_live_file_error(err);
@@ -278,10 +285,14 @@ impl Cx {
                         err.span.end.line,
                         err.span.end.column,
                         err.message,
-                        crate::log::LogLevel::Error
+                        if is_warning {crate::log::LogLevel::Warning} else {crate::log::LogLevel::Error}
                     );
                     continue
                 }
+                if is_warning {
+                    warning!("check_live_file_watcher: Warning expanding live file {}", err);
+                    continue
+                }
                 error!("check_live_file_watcher: Error expanding live file {}", err);
             }
             self.draw_shaders.reset_for_live_reload();
@@ -302,7 +313,7 @@ impl Cx {
         }*/
         //let dt = crate::profile_start();
         
-        live_registry.expand_all_documents(&mut errs);
+        live_registry.expand_all_documents(&mut errs, false);
         //crate::profile_end!(dt);
         
         // lets evaluate all expressions in the main module
@@ -313,6 +324,7 @@ impl Cx {
         }*/
                 
         for err in errs {
+            let is_warning = err.severity == LiveErrorSeverity::Warning;
             if std::env::args().find(|v| v == "--message-format=json").is_some(){
                 let err = live_registry.live_error_to_live_file_error(err);
                 //println!("Error expanding live file {}", err);
@@ -323,10 +335,14 @@ impl Cx {
                     err.span.end.line,
                     err.span.end.column,
                     err.message,
-                    crate::log::LogLevel::Error
+                    if is_warning {crate::log::LogLevel::Warning} else {crate::log::LogLevel::Error}
                 );
                 continue
             }
+            if is_warning {
+                println!("Warning expanding live file {}", live_registry.live_error_to_live_file_error(err));
+                continue
+            }
             println!("Error expanding live file {}", live_registry.live_error_to_live_file_error(err));
         }
     }
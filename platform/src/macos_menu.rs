@@ -1,6 +1,7 @@
 use {
     crate::{
         makepad_live_id::LiveId,
+        makepad_micro_serde::*,
         event::KeyCode
     },
 };
@@ -13,7 +14,7 @@ pub struct CxCommandSetting {
     pub enabled: bool
 }*/
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, SerBin, DeBin, SerJson, DeJson)]
 pub enum MacosMenu {
     Main {items:Vec<MacosMenu>},
     Item {name: String, command:LiveId, shift:bool, key:KeyCode, enabled: bool},
@@ -604,7 +604,11 @@ impl CxOsApi for Cx {
     }
     
     fn start_stdin_service(&mut self) {
-        self.start_xpc_service()
+        // without the xpc service stdin children can't share their framebuffers with us, but
+        // everything else keeps working
+        if let Err(err) = self.start_xpc_service() {
+            crate::error!("Cannot start the metal xpc service: {}", err);
+        }
     }
     
     fn seconds_since_app_start(&self)->f64{
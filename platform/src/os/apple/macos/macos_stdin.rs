@@ -3,10 +3,12 @@ use {
         io,
         io::prelude::*,
         io::BufReader,
+        panic::{self, AssertUnwindSafe},
         path::Path,
         fs,
         process::Command,
         sync::mpsc,
+        time::Instant,
     },
     crate::{
         makepad_live_id::*,
@@ -22,11 +24,13 @@ use {
             apple_sys::*,
             metal_xpc::{
                 xpc_service_proxy,
+                xpc_service_name,
+                XPC_SERVICE_NAME_VAR,
                 //xpc_service_proxy_poll_run_loop,
                 fetch_xpc_service_texture,
             },
             metal::{MetalCx, DrawPassMode},
-            cx_stdin::{HostToStdin, PresentableDraw, StdinToHost, Swapchain, PollTimer},
+            cx_stdin::{HostToStdin, HostToStdinReceiver, PresentableDraw, StdinToHost, Swapchain, PollTimer},
         },
         pass::{CxPassParent, PassClearColor, CxPassColorTexture},
         cx_api::CxOsOp,
@@ -49,6 +53,17 @@ impl StdinWindow{
             rx_fb
         }
     }
+    
+    /// Drops the framebuffer textures and any that are still arriving, so the next `Tick`
+    /// fetches them again instead of drawing into ones a panic may have left half set up.
+    fn reset_presentable_state(&mut self){
+        if let Some(swapchain) = &mut self.swapchain{
+            for presentable_image in &mut swapchain.presentable_images{
+                presentable_image.image = None;
+            }
+        }
+        while self.rx_fb.try_recv().is_ok() {}
+    }
 }
 
 impl Cx {
@@ -145,8 +160,11 @@ impl Cx {
         
         // lets create 2 windows
 
+        let mut json_msg_rx = HostToStdinReceiver::new(json_msg_rx);
+        let mut redraw_requested = false;
         while let Ok(msg) =  json_msg_rx.recv(){
-            match msg {
+            // a panicking handler only loses this message, instead of taking the whole child down
+            let result = panic::catch_unwind(AssertUnwindSafe(|| match msg {
                /* HostToStdin::ReloadFile {file, contents} => {
                     // alright lets reload this file in our DSL system
                     let _ = self.live_file_change_sender.send(vec![LiveFileChange{
@@ -200,6 +218,11 @@ impl Cx {
                     let  (window_id,pos) = self.windows.window_id_contains(dvec2(e.x, e.y));
                     self.call_event_handler(&Event::Scroll(e.into_event(window_id, pos)));
                 }
+                HostToStdin::DragDrop(e) => {
+                    let (_, pos) = self.windows.window_id_contains(dvec2(e.x, e.y));
+                    self.call_event_handler(&e.into_event(pos));
+                    self.drag_drop.cycle_drag();
+                }
                 HostToStdin::WindowGeomChange { dpi_factor, left, top, width, height, window_id } => {
                     let window_id = CxWindowPool::from_usize(window_id);
                     
@@ -233,6 +256,7 @@ impl Cx {
                     self.stdin_handle_platform_ops(metal_cx, &mut stdin_windows);
                 }
                 HostToStdin::Tick=>{
+                    redraw_requested = false;
                     for stdin_window in &mut stdin_windows{
                         if stdin_window.swapchain.is_some() {
                             let swapchain = stdin_window.swapchain.as_mut().unwrap();
@@ -289,19 +313,42 @@ impl Cx {
                     }
                     
                     if self.need_redrawing() {
+                        let draw_start = Instant::now();
                         self.call_draw_event();
+                        let compile_start = Instant::now();
                         self.mtl_compile_shaders(metal_cx);
+                        let repaint_start = Instant::now();
+                        self.stdin_handle_repaint(metal_cx, &mut stdin_windows, self.os.stdin_timers.time_now() as f32);
+                        let repaint_end = Instant::now();
+                        let _ = io::stdout().write_all(StdinToHost::FrameStats{
+                            draw_ms: ((compile_start - draw_start) + (repaint_end - repaint_start)).as_secs_f64() * 1000.0,
+                            shader_compile_ms: (repaint_start - compile_start).as_secs_f64() * 1000.0,
+                        }.to_json().as_bytes());
+                    }
+                    else{
+                        self.stdin_handle_repaint(metal_cx, &mut stdin_windows, self.os.stdin_timers.time_now() as f32);
                     }
-                    self.stdin_handle_repaint(metal_cx, &mut stdin_windows, self.os.stdin_timers.time_now() as f32);
                 }
+            }));
+            if let Err(payload) = result {
+                for stdin_window in &mut stdin_windows {
+                    stdin_window.reset_presentable_state();
+                }
+                self.stdin_recover_from_panic(payload);
             }
+            self.stdin_request_redraw(&mut redraw_requested);
         }
-        // we should poll our runloop
-        
-        //xpc_service_proxy_poll_run_loop();
+        // stdin was closed (or unreadable), so the host is gone. tear down in order so the shared
+        // framebuffers and the xpc proxy are released and the app's Drop impls run before we exit
+        for stdin_window in &mut stdin_windows {
+            stdin_window.swapchain = None;
+            while stdin_window.rx_fb.try_recv().is_ok() {}
+        }
+        drop(service_proxy);
+        self.call_event_handler(&Event::Shutdown);
     }
     
-    pub(crate)fn start_xpc_service(&mut self){
+    pub(crate)fn start_xpc_service(&mut self)->Result<(), String>{
         
         pub fn mkdir(path: &Path) -> Result<(), String> {
             match fs::create_dir_all(path) { 
@@ -348,7 +395,13 @@ impl Cx {
             std::str::from_utf8(&buf[0..end]).unwrap().to_string()
         }
         
-        let exe_path = get_exe_path();
+        // resolve symlinks so the same binary reached through another path doesn't count as a change
+        let exe_path = fs::canonicalize(get_exe_path())
+            .map( | path | path.to_string_lossy().to_string())
+            .unwrap_or_else( | _ | get_exe_path());
+        let service_name = xpc_service_name();
+        // children we spawn with --stdin-loop connect to the service under the same name
+        std::env::set_var(XPC_SERVICE_NAME_VAR, &service_name);
         
         let plist_body = format!(r#"
             <?xml version="1.0" encoding="UTF-8"?>
@@ -356,7 +409,7 @@ impl Cx {
             <plist version="1.0">
             <dict>
                 <key>Label</key>
-                <string>dev.makepad.metalxpc</string>
+                <string>{service_name}</string>
                 <key>Program</key>
                 <string>{exe_path}</string>
                 <key>ProgramArguments</key>
@@ -364,9 +417,14 @@ impl Cx {
                     <string>{exe_path}</string>
                     <string>--metal-xpc</string>
                 </array>
+                <key>EnvironmentVariables</key>
+                <dict>
+                    <key>{XPC_SERVICE_NAME_VAR}</key>
+                    <string>{service_name}</string>
+                </dict>
                 <key>MachServices</key>
                 <dict>
-                    <key>dev.makepad.metalxpc</key>
+                    <key>{service_name}</key>
                     <true/>
                 </dict>
             </dict>
@@ -374,21 +432,39 @@ impl Cx {
             "#,
         );
         // lets write our service
-        let home = std::env::var("HOME").unwrap();
-        let plist_path = format!("{}/Library/LaunchAgents/dev.makepad.xpc.plist", home);
-        let cwd = std::env::current_dir().unwrap();
+        let home = std::env::var("HOME").map_err( | e | format!("Cant read HOME {:?}", e)) ?;
+        let plist_path = format!("{}/Library/LaunchAgents/{}.plist", home, service_name);
+        let cwd = std::env::current_dir().map_err( | e | format!("Cant read current dir {:?}", e)) ?;
         
-        if let Ok(old) = fs::read_to_string(Path::new(&plist_path)){
-            if old == plist_body{
-                return
-            }
-            if std::env::args().find( | v | v == "--stdin-loop").is_some() {
-                return
+        let old_plist_body = fs::read_to_string(Path::new(&plist_path)).ok();
+        if old_plist_body.is_some() && std::env::args().find( | v | v == "--stdin-loop").is_some() {
+            return Ok(())
+        }
+        // older builds registered a single agent for every app under a fixed plist name,
+        // which would keep claiming the old Mach service, so retire it
+        let legacy_plist_path = format!("{}/Library/LaunchAgents/dev.makepad.xpc.plist", home);
+        if Path::new(&legacy_plist_path).exists() {
+            let _ = shell(&cwd, "launchctl",&["unload",&legacy_plist_path]);
+            fs::remove_file(&legacy_plist_path)
+                .map_err( | e | format!("Cant remove {:?} {:?}", legacy_plist_path, e)) ?;
+        }
+        let is_stale = old_plist_body.as_deref() != Some(plist_body.as_str());
+        let is_loaded = Command::new("launchctl")
+            .args(["list", &service_name])
+            .output()
+            .map( | output | output.status.success())
+            .unwrap_or(false);
+        // only touch launchd when the agent points at another binary or isn't running
+        if !is_stale && is_loaded {
+            return Ok(())
+        }
+        if is_stale {
+            if is_loaded {
+                shell(&cwd, "launchctl",&["unload",&plist_path]) ?;
             }
+            write_text(Path::new(&plist_path), &plist_body) ?;
         }
-        shell(&cwd, "launchctl",&["unload",&plist_path]).unwrap();
-        write_text(Path::new(&plist_path), &plist_body).unwrap();
-        shell(&cwd, "launchctl",&["load",&plist_path]).unwrap();
+        shell(&cwd, "launchctl",&["load",&plist_path])
     }
     
     
@@ -403,10 +479,17 @@ impl Cx {
                     window.is_created = true;
                     // we should call to the host to make a window with this id
                     let _ = io::stdout().write_all(StdinToHost::CreateWindow{window_id:window_id.id(),kind_id:window.kind_id}.to_json().as_bytes());
+                    let _ = io::stdout().write_all(StdinToHost::SetWindowTitle{window_id:window_id.id(),title:window.create_title.clone()}.to_json().as_bytes());
                 },
                 CxOsOp::SetCursor(cursor) => {
                     let _ = io::stdout().write_all(StdinToHost::SetCursor(cursor).to_json().as_bytes());
                 },
+                CxOsOp::StartDragging(items) => {
+                    let _ = io::stdout().write_all(StdinToHost::StartDragging{items}.to_json().as_bytes());
+                },
+                CxOsOp::UpdateMacosMenu(menu) => {
+                    let _ = io::stdout().write_all(StdinToHost::UpdateMenu(menu).to_json().as_bytes());
+                },
                 CxOsOp::StartTimer {timer_id, interval, repeats} => {
                     self.os.stdin_timers.timers.insert(timer_id, PollTimer::new(interval, repeats));
                 },
@@ -434,9 +517,7 @@ impl Cx {
                 CxOsOp::HideTextIME => {},
                 CxOsOp::SetCursor(_cursor) => {},
                 CxOsOp::StartTimer {timer_id, interval, repeats} => {},
-                CxOsOp::StopTimer(timer_id) => {},
-                CxOsOp::StartDragging(dragged_item) => {}
-                CxOsOp::UpdateMenu(menu) => {}*/
+                CxOsOp::StopTimer(timer_id) => {}*/
             }
         }
     }
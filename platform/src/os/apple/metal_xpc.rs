@@ -50,13 +50,44 @@ pub fn xpc_service_proxy_poll_run_loop() {
     }
 }*/
 
+pub const XPC_SERVICE_NAME_VAR: &str = "MAKEPAD_METAL_XPC_SERVICE";
+
+// the host registers the service under this name and hands it down through the environment, both
+// to the stdin children it spawns and to the service itself (via the launchd plist)
+pub fn xpc_service_name() -> String {
+    if let Ok(name) = std::env::var(XPC_SERVICE_NAME_VAR) {
+        return name
+    }
+    let suffix = unsafe {
+        let bundle: ObjcId = msg_send![class!(NSBundle), mainBundle];
+        let bundle_id: ObjcId = msg_send![bundle, bundleIdentifier];
+        if bundle_id != nil {
+            nsstring_to_string(bundle_id)
+        }
+        else {
+            std::env::current_exe().ok()
+                .and_then( | path | path.file_stem().map( | stem | stem.to_string_lossy().to_string()))
+                .unwrap_or_default()
+        }
+    };
+    let suffix: String = suffix.chars()
+        .map( | c | if c.is_ascii_alphanumeric() || c == '.' || c == '-' {c} else {'-'})
+        .collect();
+    if suffix.is_empty() {
+        "dev.makepad.metalxpc".to_string()
+    }
+    else {
+        format!("dev.makepad.metalxpc.{}", suffix)
+    }
+}
+
 pub fn xpc_service_proxy() -> RcObjcId {
     unsafe {
         if METAL_XPC_CLASSES == 0 as *const _ {
             METAL_XPC_CLASSES = Box::into_raw(Box::new(MetalXPCClasses::new()));
         } 
         let connection: ObjcId = msg_send![class!(NSXPCConnection), new];
-        let nsstring = str_to_nsstring("dev.makepad.metalxpc");
+        let nsstring = str_to_nsstring(&xpc_service_name());
         let () = msg_send![connection, initWithMachServiceName: nsstring options: 0];
         let iface: ObjcId = msg_send![
             class!(NSXPCInterface),
@@ -113,7 +144,7 @@ pub fn start_xpc_service() {
         METAL_XPC_CLASSES = Box::into_raw(Box::new(MetalXPCClasses::new()));
         METAL_XPC_STORAGE = Box::into_raw(Box::new(MetalXPCStorage::default()));
         let delegate: ObjcId = msg_send![get_metal_xpc_classes().xpc_service_delegate, new];
-        let nsstring = str_to_nsstring("dev.makepad.metalxpc");
+        let nsstring = str_to_nsstring(&xpc_service_name());
         let listener: ObjcId = msg_send![class!(NSXPCListener), new];
         let () = msg_send![listener, setDelegate: delegate];
         let () = msg_send![listener, initWithMachServiceName: nsstring];
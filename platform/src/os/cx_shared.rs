@@ -1,6 +1,7 @@
 
 use {
     std::collections::{HashSet, HashMap},
+    std::panic::{self, AssertUnwindSafe},
     crate::{
         cx_api::CxOsApi,
         cx::Cx,
@@ -119,9 +120,7 @@ impl Cx {
         self.event_id += 1;
         if Cx::has_studio_web_socket(){
             let start = self.seconds_since_app_start();
-            let mut event_handler = self.event_handler.take().unwrap();
-            event_handler(self, event);
-            self.event_handler = Some(event_handler);
+            self.call_event_handler_fn(event);
             let end = self.seconds_since_app_start();
             Cx::send_studio_message(AppToStudio::EventSample(EventSample{
                 event_u32: event.to_u32(),
@@ -131,9 +130,18 @@ impl Cx {
             }))
         }
         else{
-            let mut event_handler = self.event_handler.take().unwrap();
-            event_handler(self, event);
-            self.event_handler = Some(event_handler);
+            self.call_event_handler_fn(event);
+        }
+    }
+    
+    fn call_event_handler_fn(&mut self, event: &Event) {
+        let mut event_handler = self.event_handler.take().unwrap();
+        // the handler is put back before a panic carries on unwinding, so a caller that catches
+        // it (like the stdin loop) can keep sending events
+        let result = panic::catch_unwind(AssertUnwindSafe(|| event_handler(self, event)));
+        self.event_handler = Some(event_handler);
+        if let Err(payload) = result {
+            panic::resume_unwind(payload);
         }
     }
     
@@ -1,10 +1,16 @@
 #![allow(dead_code)]
 use {
+    std::any::Any,
     std::cell::Cell,
     std::collections::HashMap,
+    std::io::{self, Write},
+    std::sync::{Arc, Mutex},
+    std::sync::mpsc::{Receiver, RecvError},
     crate::{
         cx::Cx,
+        makepad_live_compiler::LiveFileError,
         cursor::MouseCursor,
+        macos_menu::MacosMenu,
         makepad_micro_serde::*,
         makepad_math::{dvec2,DVec2},
         window::{WindowId},
@@ -19,6 +25,10 @@ use {
             MouseDownEvent,
             MouseUpEvent,
             MouseMoveEvent,
+            DragEvent,
+            DropEvent,
+            DragItem,
+            DragResponse,
         }
     }
 };
@@ -434,6 +444,45 @@ impl StdinScroll {
     }
 }
 
+#[derive(Clone, Copy, Debug, SerBin, DeBin, SerJson, DeJson, PartialEq)]
+pub enum StdinDragDropKind{
+    Drag,
+    Drop,
+    End
+}
+
+#[derive(Clone, Debug, SerBin, DeBin, SerJson, DeJson, PartialEq)]
+pub struct StdinDragDrop{
+    pub kind: StdinDragDropKind,
+    pub x: f64,
+    pub y: f64,
+    pub items: Vec<DragItem>,
+    pub modifiers: StdinKeyModifiers
+}
+
+impl StdinDragDrop {
+    pub fn into_event(self, pos: DVec2) -> Event {
+        let abs = dvec2(self.x - pos.x, self.y - pos.y);
+        let modifiers = self.modifiers.into_key_modifiers();
+        match self.kind{
+            StdinDragDropKind::Drag => Event::Drag(DragEvent{
+                modifiers,
+                handled: Arc::new(Mutex::new(false)),
+                abs,
+                items: Arc::new(self.items),
+                response: Arc::new(Mutex::new(DragResponse::None)),
+            }),
+            StdinDragDropKind::Drop => Event::Drop(DropEvent{
+                modifiers,
+                handled: Arc::new(Mutex::new(false)),
+                abs,
+                items: Arc::new(self.items),
+            }),
+            StdinDragDropKind::End => Event::DragEnd,
+        }
+    }
+}
+
 #[derive(Clone, Debug, SerBin, DeBin, SerJson, DeJson)]
 pub enum HostToStdin{
     Swapchain(SharedSwapchain),
@@ -463,6 +512,7 @@ pub enum HostToStdin{
     KeyUp(KeyEvent),
     TextInput(TextInputEvent),
     Scroll(StdinScroll),
+    DragDrop(StdinDragDrop),
     /*ReloadFile{
         file:String,
         contents:String
@@ -505,7 +555,18 @@ pub enum StdinToHost {
     ReadyToStart,
     SetCursor(MouseCursor),
     // the client is done drawing, and the texture is completely updated
-    DrawCompleteAndFlip(PresentableDraw)
+    DrawCompleteAndFlip(PresentableDraw),
+    // a live-reload or shader compile error, line and col are zero-based
+    Error{file: String, line: u32, col: u32, message: String},
+    // cpu time spent on a frame that needed redrawing, sent after issuing its draw calls
+    FrameStats{draw_ms: f64, shader_compile_ms: f64},
+    // the host owns the native window, so it has to start the drag session
+    StartDragging{items: Vec<DragItem>},
+    SetWindowTitle{window_id: usize, title: String},
+    UpdateMenu(MacosMenu),
+    // there is drawing or animation pending, so the host should send a Tick on its next frame
+    // instead of waiting for its timer
+    RequestRedraw,
 }
 
 impl StdinToHost{
@@ -524,8 +585,77 @@ impl HostToStdin{
     }
 }
 
+/// Receiving end of the messages read from stdin, which folds mouse moves that are already
+/// queued up behind each other into the latest one, so a fast stream of moves can't starve the
+/// ticks. Every other message is delivered in order.
+pub struct HostToStdinReceiver{
+    receiver: Receiver<HostToStdin>,
+    pending: Option<HostToStdin>,
+}
+
+impl HostToStdinReceiver{
+    pub fn new(receiver: Receiver<HostToStdin>)->Self{
+        Self{
+            receiver,
+            pending: None
+        }
+    }
+    
+    pub fn recv(&mut self)->Result<HostToStdin, RecvError>{
+        let msg = match self.pending.take(){
+            Some(msg) => msg,
+            None => self.receiver.recv()?
+        };
+        if let HostToStdin::MouseMove(mut e) = msg{
+            while let Ok(next_msg) = self.receiver.try_recv(){
+                match next_msg{
+                    HostToStdin::MouseMove(next_e) => e = next_e,
+                    next_msg => {
+                        self.pending = Some(next_msg);
+                        break;
+                    }
+                }
+            }
+            return Ok(HostToStdin::MouseMove(e))
+        }
+        Ok(msg)
+    }
+}
+
 impl Cx {
+    pub (crate) fn stdin_send_error(err: &LiveFileError) {
+        let _ = io::stdout().write_all(StdinToHost::Error{
+            file: err.file.clone(),
+            line: err.span.start.line,
+            col: err.span.start.column,
+            message: err.message.clone(),
+        }.to_json().as_bytes());
+    }
     
+    /// Logs a panic that unwound out of the handling of a host message as an error, and
+    /// redraws everything so the passes it may have left half drawn are never presented.
+    pub (crate) fn stdin_recover_from_panic(&mut self, payload: Box<dyn Any + Send>) {
+        let message = if let Some(message) = payload.downcast_ref::<&str>() {
+            message.to_string()
+        }
+        else if let Some(message) = payload.downcast_ref::<String>() {
+            message.clone()
+        }
+        else {
+            "unknown panic payload".to_string()
+        };
+        crate::error!("event handler panicked: {}", message);
+        self.redraw_all();
+    }
+    
+    /// Asks the host for a `Tick` on its next frame when a draw or a next frame is pending, instead
+    /// of waiting for the host's timer. Only one request is sent until that tick arrives.
+    pub (crate) fn stdin_request_redraw(&self, redraw_requested: &mut bool) {
+        if !*redraw_requested && (self.need_redrawing() || self.new_next_frames.len() != 0) {
+            *redraw_requested = true;
+            let _ = io::stdout().write_all(StdinToHost::RequestRedraw.to_json().as_bytes());
+        }
+    }
 }
 
 
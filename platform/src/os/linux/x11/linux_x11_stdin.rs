@@ -3,6 +3,7 @@ use {
         io,
         io::prelude::*,
         io::BufReader,
+        panic::{self, AssertUnwindSafe},
     },
     crate::{
         makepad_live_id::*,
@@ -14,7 +15,7 @@ use {
         event::WindowGeom,
         texture::{Texture, TextureFormat},
         thread::SignalToUI,
-        os::cx_stdin::{aux_chan, HostToStdin, PresentableDraw, StdinToHost, Swapchain, PollTimer},
+        os::cx_stdin::{aux_chan, HostToStdin, HostToStdinReceiver, PresentableDraw, StdinToHost, Swapchain, PollTimer},
         pass::{CxPassParent, PassClearColor, CxPassColorTexture},
         cx_api::CxOsOp,
         cx::Cx,
@@ -28,6 +29,13 @@ pub(crate) struct StdinWindow{
     present_index: usize
 }
 
+impl StdinWindow{
+    /// Starts presenting from the first image again, as the host does after a new swapchain.
+    fn reset_presentable_state(&mut self){
+        self.present_index = 0;
+    }
+}
+
 impl Cx {
     
     pub (crate) fn stdin_handle_repaint(
@@ -117,8 +125,11 @@ impl Cx {
  
         self.call_event_handler(&Event::Startup);
 
+        let mut json_msg_rx = HostToStdinReceiver::new(json_msg_rx);
+        let mut redraw_requested = false;
         while let Ok(msg) = json_msg_rx.recv(){
-            match msg {
+            // a panicking handler only loses this message, instead of taking the whole child down
+            let result = panic::catch_unwind(AssertUnwindSafe(|| match msg {
                 HostToStdin::KeyDown(e) => {
                     self.call_event_handler(&Event::KeyDown(e));
                 }
@@ -164,6 +175,11 @@ impl Cx {
                     let (window_id,pos) = self.windows.window_id_contains(dvec2(e.x, e.y));
                     self.call_event_handler(&Event::Scroll(e.into_event(window_id,pos)))
                 }
+                HostToStdin::DragDrop(e) => {
+                    let (_, pos) = self.windows.window_id_contains(dvec2(e.x, e.y));
+                    self.call_event_handler(&e.into_event(pos));
+                    self.drag_drop.cycle_drag();
+                }
                 HostToStdin::WindowGeomChange { dpi_factor, left, top, width, height, window_id } => {
                     self.windows[CxWindowPool::from_usize(window_id)].window_geom = WindowGeom {
                         dpi_factor,
@@ -222,6 +238,7 @@ impl Cx {
                 }
 
                 HostToStdin::Tick  =>  {
+                    redraw_requested = false;
 
                     // poll the service for updates
                     // check signals
@@ -254,7 +271,14 @@ impl Cx {
 
                     self.stdin_handle_repaint(&mut stdin_windows);
                 }
+            }));
+            if let Err(payload) = result {
+                for stdin_window in &mut stdin_windows {
+                    stdin_window.reset_presentable_state();
+                }
+                self.stdin_recover_from_panic(payload);
             }
+            self.stdin_request_redraw(&mut redraw_requested);
         }
     }
     
@@ -273,10 +297,17 @@ impl Cx {
                     let window = &mut self.windows[window_id];
                     window.is_created = true;
                     let _ = io::stdout().write_all(StdinToHost::CreateWindow{window_id:window_id.id(),kind_id:window.kind_id}.to_json().as_bytes());
+                    let _ = io::stdout().write_all(StdinToHost::SetWindowTitle{window_id:window_id.id(),title:window.create_title.clone()}.to_json().as_bytes());
                 },
                 CxOsOp::SetCursor(cursor) => {
                     let _ = io::stdout().write_all(StdinToHost::SetCursor(cursor).to_json().as_bytes());
                 },
+                CxOsOp::StartDragging(items) => {
+                    let _ = io::stdout().write_all(StdinToHost::StartDragging{items}.to_json().as_bytes());
+                },
+                CxOsOp::UpdateMacosMenu(menu) => {
+                    let _ = io::stdout().write_all(StdinToHost::UpdateMenu(menu).to_json().as_bytes());
+                },
                 CxOsOp::StartTimer {timer_id, interval, repeats} => {
                     self.os.stdin_timers.timers.insert(timer_id, PollTimer::new(interval, repeats));
                 },
@@ -298,9 +329,7 @@ impl Cx {
                 CxOsOp::HideTextIME => {},
                 CxOsOp::SetCursor(_cursor) => {},
                 CxOsOp::StartTimer {timer_id, interval, repeats} => {},
-                CxOsOp::StopTimer(timer_id) => {},
-                CxOsOp::StartDragging(dragged_item) => {}
-                CxOsOp::UpdateMenu(menu) => {}*/
+                CxOsOp::StopTimer(timer_id) => {}*/
             }
         }
     }
@@ -3,6 +3,7 @@ use {
         io,
         io::prelude::*,
         io::BufReader,
+        panic::{self, AssertUnwindSafe},
     },
     crate::{
         makepad_live_id::*,
@@ -16,7 +17,7 @@ use {
         thread::SignalToUI,
         os::{
             d3d11::D3d11Cx,
-            cx_stdin::{HostToStdin, PresentableDraw, StdinToHost, Swapchain},
+            cx_stdin::{HostToStdin, HostToStdinReceiver, PresentableDraw, StdinToHost, Swapchain},
         },
         pass::{CxPassParent},
         cx_api::CxOsOp,
@@ -32,6 +33,15 @@ pub(crate) struct StdinWindow{
     new_frame_being_rendered: Option<PresentableDraw>
 }
 
+impl StdinWindow{
+    /// Forgets a frame that a panic may have left marked as being rendered, which would
+    /// otherwise keep the window from ever drawing again.
+    fn reset_presentable_state(&mut self){
+        self.present_index = 0;
+        self.new_frame_being_rendered = None;
+    }
+}
+
 
 impl Cx {
     
@@ -126,9 +136,12 @@ impl Cx {
         //let mut previous_elapsed_s = 0f64;
         //let mut allow_rendering = true;
         
+        let mut json_msg_rx = HostToStdinReceiver::new(json_msg_rx);
+        let mut redraw_requested = false;
         while let Ok(msg) = json_msg_rx.recv() {
 
-            match msg {
+            // a panicking handler only loses this message, instead of taking the whole child down
+            let result = panic::catch_unwind(AssertUnwindSafe(|| match msg {
                 HostToStdin::KeyDown(e) => {
                     self.call_event_handler(&Event::KeyDown(e));
                 }
@@ -174,6 +187,11 @@ impl Cx {
                     let  (window_id,pos) = self.windows.window_id_contains(dvec2(e.x, e.y));
                     self.call_event_handler(&Event::Scroll(e.into_event(window_id, pos)));
                 }
+                HostToStdin::DragDrop(e) => {
+                    let (_, pos) = self.windows.window_id_contains(dvec2(e.x, e.y));
+                    self.call_event_handler(&e.into_event(pos));
+                    self.drag_drop.cycle_drag();
+                }
                 HostToStdin::WindowGeomChange { dpi_factor, left, top, width, height, window_id } => {
                     self.windows[CxWindowPool::from_usize(window_id)].window_geom = WindowGeom {
                         dpi_factor,
@@ -206,6 +224,7 @@ impl Cx {
                     self.stdin_handle_platform_ops(&mut stdin_windows);
                 }
                 HostToStdin::Tick =>  {
+                    redraw_requested = false;
                     
                     // probe current time
                     //let start_time = ::std::time::SystemTime::now();
@@ -270,7 +289,14 @@ impl Cx {
                     previous_tick_time_s = Some(time);
                     previous_elapsed_s = elapsed_s;*/
                 }
+            }));
+            if let Err(payload) = result {
+                for stdin_window in &mut stdin_windows {
+                    stdin_window.reset_presentable_state();
+                }
+                self.stdin_recover_from_panic(payload);
             }
+            self.stdin_request_redraw(&mut redraw_requested);
         }
     }
     
@@ -288,6 +314,7 @@ impl Cx {
                     let window = &mut self.windows[window_id];
                     window.is_created = true;
                     let _ = io::stdout().write_all(StdinToHost::CreateWindow{window_id:window_id.id(),kind_id:window.kind_id}.to_json().as_bytes());
+                    let _ = io::stdout().write_all(StdinToHost::SetWindowTitle{window_id:window_id.id(),title:window.create_title.clone()}.to_json().as_bytes());
                      
                     // lets set up our render pass target
                    /* let pass = &mut self.passes[window.main_pass_id.unwrap()];
@@ -302,6 +329,12 @@ impl Cx {
                 CxOsOp::SetCursor(cursor) => {
                     let _ = io::stdout().write_all(StdinToHost::SetCursor(cursor).to_json().as_bytes());
                 },
+                CxOsOp::StartDragging(items) => {
+                    let _ = io::stdout().write_all(StdinToHost::StartDragging{items}.to_json().as_bytes());
+                },
+                CxOsOp::UpdateMacosMenu(menu) => {
+                    let _ = io::stdout().write_all(StdinToHost::UpdateMenu(menu).to_json().as_bytes());
+                },
                 _ => ()
                 /*
                 CxOsOp::CloseWindow(_window_id) => {},
@@ -317,9 +350,7 @@ impl Cx {
                 CxOsOp::HideTextIME => {},
                 CxOsOp::SetCursor(_cursor) => {},
                 CxOsOp::StartTimer {timer_id, interval, repeats} => {},
-                CxOsOp::StopTimer(timer_id) => {},
-                CxOsOp::StartDragging(dragged_item) => {}
-                CxOsOp::UpdateMenu(menu) => {}*/
+                CxOsOp::StopTimer(timer_id) => {}*/
             }
         }
     }
@@ -47,7 +47,13 @@ pub enum FileRequest {
         id: u64,
         patch: bool
     },
-
+    /// Requests the collab server to tokenize the lines `line_start..line_end` of the file with the
+    /// given path, so that a client can highlight them without a tokenizer of its own.
+    GetTokens{
+        path: String,
+        line_start: usize,
+        line_end: usize,
+    },
 }
 
 /// A type for representing either a response or a notification from the collab server.
@@ -83,6 +89,34 @@ pub struct OpenFileResponse{
 }
 
 
+#[derive(Clone, Debug, SerBin, DeBin)]
+pub struct GetTokensResponse{
+    pub path: String,
+    /// The tokens of each requested line that exists, paired with the index of that line.
+    pub lines: Vec<(usize, Vec<Token>)>,
+}
+
+/// A token in a line, starting where the previous token ended.
+#[derive(Clone, Copy, Debug, SerBin, DeBin, PartialEq)]
+pub struct Token{
+    /// The length of the token in bytes.
+    pub len: usize,
+    pub kind: TokenKind,
+}
+
+#[derive(Clone, Copy, Debug, SerBin, DeBin, PartialEq)]
+pub enum TokenKind{
+    Unknown,
+    Comment,
+    Delimiter,
+    Identifier,
+    Keyword,
+    Number,
+    Punctuator,
+    String,
+    Whitespace,
+}
+
 /// Each `Response` corresponds to the `Request` with the same name.
 #[derive(Clone, Debug, SerBin, DeBin)]
 pub enum FileResponse {
@@ -94,6 +128,8 @@ pub enum FileResponse {
     /// The result of requesting the collab server to apply a delta to a revision of the file with
     /// the given id.
     SaveFile(Result<SaveFileResponse, FileError>),
+    /// The result of requesting the collab server to tokenize some lines of a file.
+    GetTokens(Result<GetTokensResponse, FileError>),
     
     // Existing variants...
 }
@@ -131,9 +167,10 @@ pub struct DirectoryEntry {
 #[derive(Clone, Debug, SerBin, DeBin)]
 pub enum FileNotification {
     FileChangedOnDisk(SaveFileResponse),
-    // Notifies the client that another client applied the given delta to the file with the given
-    // id. This is only sent for files for which the client is a participant.
-   // DeltaWasApplied(TextFileId),
+    /// Notifies the client that another client saved a file that this client has open. The `id`
+    /// is the one this client opened the file with. Since the notification carries the whole new
+    /// contents, a client that missed earlier ones is back in sync after applying the latest.
+    FileSavedByPeer(SaveFileResponse),
 }
 
 /// A type for representing errors from the collab server.
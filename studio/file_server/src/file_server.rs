@@ -10,11 +10,16 @@ use {
             FileResponse,
             SaveKind,
             SaveFileResponse,
-            OpenFileResponse
+            OpenFileResponse,
+            GetTokensResponse,
+            Token,
+            TokenKind,
         },
+        makepad_live_tokenizer::{Cursor, FullToken, State},
     },
     std::{
         thread,
+        collections::HashMap,
         cmp::Ordering,
         fmt,
         fs,
@@ -38,6 +43,7 @@ impl FileServer {
             next_connection_id: 0,
             shared: Arc::new(RwLock::new(Shared {
                 root_path: root_path.into(),
+                connections: Default::default(),
             })),
         }
     }
@@ -49,11 +55,16 @@ impl FileServer {
     pub fn connect(&mut self, notification_sender: Box<dyn NotificationSender>) -> FileServerConnection {
         let connection_id = ConnectionId(self.next_connection_id);
         self.next_connection_id += 1;
+        let open_files = OpenFiles::default();
+        self.shared.read().unwrap().connections.lock().unwrap().insert(
+            connection_id,
+            (notification_sender.clone(), open_files.clone())
+        );
         FileServerConnection {
-            _connection_id:connection_id,
+            connection_id,
             shared: self.shared.clone(),
-            _notification_sender: notification_sender,
-            open_files: Default::default(),
+            notification_sender,
+            open_files,
             stop_observation: Default::default()
         }
     }
@@ -62,12 +73,12 @@ impl FileServer {
 /// A connection to a collab server.
 pub struct FileServerConnection {
     // The id for this connection.
-    _connection_id: ConnectionId,
+    connection_id: ConnectionId,
     // State is shared between every connection.
     shared: Arc<RwLock<Shared >>,
     // Used to send notifications for this connection.
-    _notification_sender: Box<dyn NotificationSender>,
-    open_files: Arc<Mutex<Vec<(String, u64, Vec<u8>)>>>,
+    notification_sender: Box<dyn NotificationSender>,
+    open_files: OpenFiles,
     stop_observation: Arc<Mutex<bool>>,
 }
 
@@ -83,6 +94,7 @@ impl FileServerConnection {
             FileRequest::LoadFileTree {with_data} => FileResponse::LoadFileTree(self.load_file_tree(with_data)),
             FileRequest::OpenFile{path,id} => FileResponse::OpenFile(self.open_file(path, id)),
             FileRequest::SaveFile{path, data, id, patch} => FileResponse::SaveFile(self.save_file(path, data, id, patch)),
+            FileRequest::GetTokens{path, line_start, line_end} => FileResponse::GetTokens(self.get_tokens(path, line_start, line_end)),
         }
     }
     
@@ -176,7 +188,7 @@ impl FileServerConnection {
     fn start_observation(&self) {
         let open_files = self.open_files.clone();
         let shared = self.shared.clone();
-        let notification_sender = self._notification_sender.clone();
+        let notification_sender = self.notification_sender.clone();
         let stop_observation = self.stop_observation.clone();
         thread::spawn(move || {
             let stop = *stop_observation.lock().unwrap();
@@ -254,13 +266,16 @@ impl FileServerConnection {
         id: u64,
         patch: bool
     ) -> Result<SaveFileResponse, FileError> {
-        let mut open_files = self.open_files.lock().unwrap();
-                
-        if let Some(of) = open_files.iter_mut().find(|(cp,_,_)| *cp == child_path){
-            of.2 =  new_data.as_bytes().to_vec();
-        }
-        else{
-            open_files.push((child_path.clone(), id, new_data.as_bytes().to_vec()));
+        // Release our own open files before notifying peers, as a peer saving at the same time
+        // will want to lock them in turn.
+        {
+            let mut open_files = self.open_files.lock().unwrap();
+            if let Some(of) = open_files.iter_mut().find(|(cp,_,_)| *cp == child_path){
+                of.2 =  new_data.as_bytes().to_vec();
+            }
+            else{
+                open_files.push((child_path.clone(), id, new_data.as_bytes().to_vec()));
+            }
         }
         
         let path = self.make_full_path(&child_path);
@@ -273,16 +288,121 @@ impl FileServerConnection {
             | error | FileError::Unknown(error.to_string())
         ) ?;
         
-        Ok(SaveFileResponse{
+        let response = SaveFileResponse{
             path: child_path, 
             old_data,
             new_data,
             id,
             kind: if patch{SaveKind::Patch}else{SaveKind::Save}
+        };
+        self.notify_peers(&response);
+        Ok(response)
+    }
+    
+    // Forwards a save to every other connection that has the same file open.
+    fn notify_peers(&self, response: &SaveFileResponse) {
+        // Copy the peers out first, so the connections aren't locked while a peer's open files are.
+        let peers: Vec<Peer> = self.shared.read().unwrap()
+            .connections.lock().unwrap().iter()
+            .filter( | (connection_id, _) | **connection_id != self.connection_id)
+            .map( | (_, (notification_sender, open_files)) | (notification_sender.clone(), open_files.clone()))
+            .collect();
+        for (notification_sender, open_files) in peers {
+            let mut open_files = open_files.lock().unwrap();
+            if let Some((_, id, last_content)) = open_files.iter_mut().find(|(cp,_,_)| *cp == response.path){
+                // Update the peer's copy first, so its observer doesn't report the same change
+                // as a change on disk.
+                *last_content = response.new_data.as_bytes().to_vec();
+                notification_sender.send_notification(FileNotification::FileSavedByPeer(
+                    SaveFileResponse{
+                        id: *id,
+                        ..response.clone()
+                    }
+                ));
+            }
+        }
+    }
+    
+    // Handles a `GetTokens` request.
+    fn get_tokens(
+        &self,
+        child_path: String,
+        line_start: usize,
+        line_end: usize
+    ) -> Result<GetTokensResponse, FileError> {
+        // Prefer the contents a client last sent us over what is on disk.
+        let bytes = match self.open_files.lock().unwrap().iter().find(|(cp,_,_)| *cp == child_path){
+            Some((_, _, bytes)) => bytes.clone(),
+            None => fs::read(self.make_full_path(&child_path)).map_err(
+                | error | FileError::Unknown(error.to_string())
+            ) ?
+        };
+        let text = String::from_utf8_lossy(&bytes);
+        
+        // The tokenizer state carries over from line to line, so lines before the requested ones
+        // have to be tokenized as well.
+        let mut state = State::default();
+        let mut scratch = String::new();
+        let mut lines = Vec::new();
+        for (line_index, line) in text.split('\n').enumerate().take(line_end) {
+            let chars: Vec<char> = line.chars().collect();
+            let mut cursor = Cursor::new(&chars, &mut scratch);
+            let mut tokens = Vec::new();
+            let mut last_index = 0;
+            loop {
+                let (next_state, full_token) = state.next(&mut cursor);
+                state = next_state;
+                let full_token = if let Some(full_token) = full_token {full_token} else {break};
+                let token_chars = &chars[last_index..last_index + full_token.len];
+                last_index += full_token.len;
+                if line_index >= line_start {
+                    tokens.push(Token{
+                        len: token_chars.iter().map(|c| c.len_utf8()).sum(),
+                        kind: token_kind(&full_token.token, token_chars),
+                    });
+                }
+            }
+            if line_index >= line_start {
+                lines.push((line_index, tokens));
+            }
+        }
+        Ok(GetTokensResponse{
+            path: child_path,
+            lines
         })
     }
 }
 
+fn token_kind(token: &FullToken, chars: &[char]) -> TokenKind {
+    const KEYWORDS: &[&str] = &[
+        "Self", "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else",
+        "enum", "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "type",
+        "unsafe", "use", "where", "while",
+    ];
+    match token {
+        FullToken::Ident(_) => {
+            let ident: String = chars.iter().collect();
+            if KEYWORDS.contains(&ident.as_str()) {TokenKind::Keyword} else {TokenKind::Identifier}
+        }
+        FullToken::Lifetime => TokenKind::Identifier,
+        FullToken::Bool(_) => TokenKind::Keyword,
+        FullToken::Punct(_) => TokenKind::Punctuator,
+        FullToken::Open(_) | FullToken::Close(_) => TokenKind::Delimiter,
+        FullToken::String(_) => TokenKind::String,
+        FullToken::Color(_) | FullToken::Float(_) | FullToken::Int(_) | FullToken::OtherNumber => TokenKind::Number,
+        FullToken::Comment => TokenKind::Comment,
+        FullToken::Whitespace => TokenKind::Whitespace,
+        FullToken::Unknown => TokenKind::Unknown,
+    }
+}
+
+impl Drop for FileServerConnection {
+    fn drop(&mut self) {
+        self.shared.read().unwrap().connections.lock().unwrap().remove(&self.connection_id);
+    }
+}
+
 /// A trait for sending notifications over a connection.
 pub trait NotificationSender: Send {
     /// This method is necessary to create clones of boxed trait objects.
@@ -314,10 +434,20 @@ impl fmt::Debug for dyn NotificationSender {
     }
 }
 
+// The files a connection has open, with the id it opened each one with and its last known
+// contents.
+type OpenFiles = Arc<Mutex<Vec<(String, u64, Vec<u8>)>>>;
+
+// What a save needs to reach another connection.
+type Peer = (Box<dyn NotificationSender>, OpenFiles);
+
 // State that is shared between every connection.
 #[derive(Debug)]
 struct Shared {
     root_path: PathBuf,
+    // The notification sender and open files of every connection, so that a save can be
+    // forwarded to the other connections that have the same file open.
+    connections: Mutex<HashMap<ConnectionId, Peer>>,
 }
 
 /// An identifier for a connection.
@@ -7,4 +7,5 @@ pub use file_server::*;
 pub use makepad_micro_serde;
 pub use makepad_live_id;
 pub use makepad_file_protocol;
+pub use makepad_live_tokenizer;
 pub use makepad_file_protocol::*;
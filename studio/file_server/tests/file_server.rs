@@ -0,0 +1,39 @@
+use {
+    makepad_file_server::{FileNotification, FileRequest, FileServer},
+    std::{
+        fs,
+        sync::mpsc,
+        thread,
+        time::Duration,
+    },
+};
+
+#[test]
+fn test_concurrent_saves_do_not_deadlock() {
+    let root_path = std::env::temp_dir().join(format!("makepad_file_server_test_{}", std::process::id()));
+    fs::create_dir_all(&root_path).unwrap();
+    fs::write(root_path.join("a.rs"), "").unwrap();
+    
+    let mut server = FileServer::new(root_path.clone());
+    let (done_sender, done_receiver) = mpsc::channel();
+    for client in 0..2 {
+        let connection = server.connect(Box::new( | _notification: FileNotification | {}));
+        let done_sender = done_sender.clone();
+        thread::spawn(move || {
+            connection.handle_request(FileRequest::OpenFile {path: "a.rs".to_string(), id: client});
+            for index in 0..200 {
+                connection.handle_request(FileRequest::SaveFile {
+                    path: "a.rs".to_string(),
+                    data: format!("{} {}", client, index),
+                    id: client,
+                    patch: false,
+                });
+            }
+            done_sender.send(()).unwrap();
+        });
+    }
+    for _ in 0..2 {
+        done_receiver.recv_timeout(Duration::from_secs(10)).expect("saves deadlocked");
+    }
+    let _ = fs::remove_dir_all(&root_path);
+}
@@ -406,6 +406,23 @@ impl MatchEvent for App{
                             }
                         }
                     }
+                    StdinToHost::Error{..} => {
+                        // never forwarded, the build manager logs and decorates errors itself
+                    }
+                    StdinToHost::FrameStats{..} => {
+                    }
+                    StdinToHost::StartDragging{items} => {
+                        cx.start_dragging(items);
+                    }
+                    StdinToHost::SetWindowTitle{window_id, title} => {
+                        dock.set_tab_title(cx, build_id.add(window_id as u64), title);
+                    }
+                    StdinToHost::UpdateMenu(_menu) => {
+                        // the studio window keeps its own menu
+                    }
+                    StdinToHost::RequestRedraw => {
+                        self.data.build_manager.request_redraw(cx, build_id);
+                    }
                     StdinToHost::DrawCompleteAndFlip(presentable_draw) => {
                         if let Some(mut dock) = dock.borrow_mut() {
                             for (_, (_, item)) in dock.items().iter() {
@@ -6,8 +6,8 @@ use {
         makepad_micro_serde::*,
         makepad_platform::makepad_live_compiler::LiveFileChange,
         makepad_platform::os::cx_stdin::{
-            HostToStdin, StdinKeyModifiers, StdinMouseDown, StdinMouseMove, StdinMouseUp,
-            StdinScroll, StdinToHost,
+            HostToStdin, StdinDragDrop, StdinDragDropKind, StdinKeyModifiers, StdinMouseDown,
+            StdinMouseMove, StdinMouseUp, StdinScroll, StdinToHost,
         },
         makepad_platform::studio::{
             DesignerComponentPosition,
@@ -24,7 +24,7 @@ use {
     makepad_http::server::*,
     std::{
         cell::RefCell,
-        collections::{hash_map, HashMap},
+        collections::{hash_map, HashMap, HashSet},
         fs::File,
         io::prelude::*,
         net::{SocketAddr, UdpSocket},
@@ -130,6 +130,9 @@ pub struct BuildManager {
     pub recv_studio_msg: ToUIReceiver<(LiveId, AppToStudioVec)>,
     pub recv_external_ip: ToUIReceiver<SocketAddr>,
     pub tick_timer: Timer,
+    /// Builds that asked for a tick, answered together on the next frame.
+    redraw_requests: HashSet<LiveId>,
+    redraw_next_frame: NextFrame,
     pub designer_state: DesignerState,
     //pub send_file_change: FromUISender<LiveFileChange>,
     pub active_build_websockets: Arc<Mutex<RefCell<Vec<(u64, LiveId, mpsc::Sender<Vec<u8>>)>>>>,
@@ -243,6 +246,15 @@ impl BuildManager {
         self.clients[0].send_cmd_with_id(item_id, BuildCmd::HostToStdin(msg.to_json()));
     }
 
+    /// Answers a `RequestRedraw` with a tick on the next frame, so a build asking after every
+    /// message still gets at most one tick per frame.
+    pub fn request_redraw(&mut self, cx: &mut Cx, build_id: LiveId) {
+        if self.redraw_requests.is_empty() {
+            self.redraw_next_frame = cx.new_next_frame();
+        }
+        self.redraw_requests.insert(build_id);
+    }
+
     pub fn update_run_list(&mut self, _cx: &mut Cx) {
         self.binaries.clear();
         match shell_env_cap(&[], &self.root_path, "cargo", &["run", "--bin"]) {
@@ -356,8 +368,15 @@ impl BuildManager {
 
     pub fn handle_event(&mut self, cx: &mut Cx, event: &Event, file_system: &mut FileSystem) {
         if let Some(_) = self.tick_timer.is_event(event) {
+            // the broadcast answers any pending requests as well
+            self.redraw_requests.clear();
             self.broadcast_to_stdin(HostToStdin::Tick);
         }
+        if let Some(_) = self.redraw_next_frame.is_event(event) {
+            for build_id in std::mem::take(&mut self.redraw_requests) {
+                self.send_host_to_stdin(build_id, HostToStdin::Tick);
+            }
+        }
 
         match event {
             Event::MouseDown(e) => {
@@ -414,6 +433,33 @@ impl BuildManager {
                     modifiers: StdinKeyModifiers::from_key_modifiers(&e.modifiers),
                 }));
             }
+            Event::Drag(e) => {
+                self.broadcast_to_stdin(HostToStdin::DragDrop(StdinDragDrop {
+                    kind: StdinDragDropKind::Drag,
+                    x: e.abs.x,
+                    y: e.abs.y,
+                    items: e.items.as_ref().clone(),
+                    modifiers: StdinKeyModifiers::from_key_modifiers(&e.modifiers),
+                }));
+            }
+            Event::Drop(e) => {
+                self.broadcast_to_stdin(HostToStdin::DragDrop(StdinDragDrop {
+                    kind: StdinDragDropKind::Drop,
+                    x: e.abs.x,
+                    y: e.abs.y,
+                    items: e.items.as_ref().clone(),
+                    modifiers: StdinKeyModifiers::from_key_modifiers(&e.modifiers),
+                }));
+            }
+            Event::DragEnd => {
+                self.broadcast_to_stdin(HostToStdin::DragDrop(StdinDragDrop {
+                    kind: StdinDragDropKind::End,
+                    x: 0.0,
+                    y: 0.0,
+                    items: Vec::new(),
+                    modifiers: Default::default(),
+                }));
+            }
             _ => (),
         }
 
@@ -585,6 +631,46 @@ impl BuildManager {
                     BuildClientMessage::LogItem(LogItem::StdinToHost(line)) => {
                         let msg: Result<StdinToHost, DeJsonErr> = DeJson::deserialize_json(&line);
                         match msg {
+                            Ok(StdinToHost::Error{file, line, col, message}) => {
+                                let file_id = file_system.path_to_file_node_id(&file);
+                                let position = text::Position {
+                                    line_index: line as usize,
+                                    byte_index: match file_id {
+                                        Some(file_id) => file_system.char_column_to_byte_index(file_id, line as usize, col as usize),
+                                        None => col as usize,
+                                    },
+                                };
+                                if let Some(file_id) = file_id {
+                                    file_system.add_decoration(
+                                        file_id,
+                                        Decoration::new(
+                                            0,
+                                            position,
+                                            position,
+                                            DecorationType::Error,
+                                        ),
+                                    );
+                                    cx.action(AppAction::RedrawFile(file_id))
+                                }
+                                // errors without a file can't be located, so they are logged bare
+                                let item = if file.is_empty() {
+                                    LogItem::Bare(LogItemBare {
+                                        level: LogLevel::Error,
+                                        line: message,
+                                    })
+                                } else {
+                                    LogItem::Location(LogItemLocation {
+                                        level: LogLevel::Error,
+                                        file_name: file,
+                                        start: position,
+                                        end: position,
+                                        message,
+                                        explanation: None,
+                                    })
+                                };
+                                log.push((wrap.cmd_id, item));
+                                cx.action(AppAction::RedrawLog)
+                            }
                             Ok(msg) => cx.action(BuildManagerAction::StdinToHost {
                                 build_id: wrap.cmd_id,
                                 msg,
@@ -1,8 +1,9 @@
 use {
     std::collections::{HashMap, hash_map},
     std::path::Path,
+    std::rc::Rc,
     crate::{
-        makepad_code_editor::{CodeDocument, decoration::{Decoration, DecorationSet}, CodeSession},
+        makepad_code_editor::{CodeDocument, decoration::{Decoration, DecorationSet}, CodeSession, Settings},
         makepad_platform::makepad_live_compiler::LiveFileChange,
         makepad_widgets::*,
         makepad_widgets::file_tree::*,
@@ -125,6 +126,17 @@ impl FileSystem {
         self.path_to_file_node_id.get(path).cloned()
     }
     
+    /// Converts a zero-based char column on a line of the given file into the byte index the
+    /// editor positions use. Files that aren't open yet are taken to be ASCII.
+    pub fn char_column_to_byte_index(&self, file_id: LiveId, line_index: usize, column: usize) -> usize {
+        if let Some(OpenDocument::Code(doc)) = self.open_documents.get(&file_id) {
+            if let Some(line) = doc.as_text().as_lines().get(line_index) {
+                return line.char_indices().nth(column).map_or(line.len(), |(byte_index, _)| byte_index)
+            }
+        }
+        column
+    }
+    
     pub fn file_node_id_to_path(&self, file_id:LiveId) -> Option<&str> {
         for (path, id) in &self.path_to_file_node_id{
             if *id == file_id{
@@ -146,12 +158,21 @@ impl FileSystem {
     pub fn get_session_mut(&mut self, tab_id: LiveId) -> Option<&mut EditSession> {
         // lets see if we have a document yet
         if let Some(file_id) = self.tab_id_to_file_node_id.get(&tab_id) {
+            let is_rust = self.file_node_id_to_path(*file_id).is_some_and(|path| path.ends_with(".rs"));
             match self.open_documents.get(file_id){
                 Some(OpenDocument::Code(document))=>{
                     return Some(match self.tab_id_to_session.entry(tab_id) {
                         hash_map::Entry::Occupied(o) => o.into_mut(),
                         hash_map::Entry::Vacant(v) => {
-                            v.insert(EditSession::Code(CodeSession::new(document.clone())))            
+                            let mut session = CodeSession::new(document.clone());
+                            if is_rust {
+                                // continue // comments on enter
+                                session.set_settings(Rc::new(Settings {
+                                    line_comment: Some("//".to_string()),
+                                    ..Settings::default()
+                                }));
+                            }
+                            v.insert(EditSession::Code(session))
                         }
                     })
                 }
@@ -223,16 +244,20 @@ impl FileSystem {
                             // ok we saved a file, we should check however what changed
                             // to see if we need a recompile
                             
+                        },
+                        FileResponse::GetTokens(_) => {
+                            // the studio tokenizes its open documents itself
                         }
                     },
                     FileClientMessage::Notification(notification) => {
                         match notification{
-                            FileNotification::FileChangedOnDisk(response)=>{
+                            FileNotification::FileChangedOnDisk(response) |
+                            FileNotification::FileSavedByPeer(response)=>{
                                //println!("FILE CHANGED ON DISK {}", response.path);
                                 if let Some(file_id) = self.path_to_file_node_id.get(&response.path){
                                     
                                     if let Some(OpenDocument::Code(doc)) = self.open_documents.get_mut(&file_id){
-                                        doc.replace(response.new_data.clone().into());
+                                        doc.set_text(response.new_data.clone().into());
                                     }
                                     ui.redraw(cx);
                                 }
@@ -279,7 +304,7 @@ impl FileSystem {
                                     file_name: self.file_node_id_to_path(file_id).unwrap().to_string(),
                                     content: combined_data.to_string(),
                                 }));
-                                doc.replace(combined_data.into());
+                                doc.set_text(combined_data.into());
                             }
                         }
                         _ => {
@@ -398,7 +423,7 @@ impl FileSystem {
     pub fn replace_code_document(&self, file_id:LiveId, text:&str){
         match self.open_documents.get(&file_id){
             Some(OpenDocument::Code(doc))=>{
-                doc.replace(text.into());
+                doc.set_text(text.into());
             }
             _=>()
         }
@@ -417,7 +442,7 @@ impl FileSystem {
     pub fn file_id_as_string(&self, file_id: LiveId)->Option<String>{
         match self.open_documents.get(&file_id){
             Some(OpenDocument::Code(doc))=>{
-                Some(doc.as_text().to_string())
+                Some(doc.serialize())
             }
             Some(OpenDocument::CodeLoading(_))=>{
                 None